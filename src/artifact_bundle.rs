@@ -0,0 +1,491 @@
+//! Artifact bundles: minified files and sourcemaps packed into one zip.
+//!
+//! An artifact bundle is a zip archive with a `manifest.json` at its root
+//! that describes all other files in the archive:
+//!
+//! ```json
+//! {
+//!   "files": {
+//!     "files/_/_/app.min.js": {
+//!       "url": "~/app.min.js",
+//!       "type": "minified_source",
+//!       "debug_id": "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab",
+//!       "sourcemap": "~/app.min.js.map"
+//!     },
+//!     "files/_/_/app.min.js.map": {
+//!       "url": "~/app.min.js.map",
+//!       "type": "source_map",
+//!       "debug_id": "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! URLs starting with `~/` match any scheme and host.  Opening a bundle
+//! only reads the manifest, files are read and decoded when they are looked
+//! up.
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, Write};
+#[cfg(any(unix, windows, target_os = "redox"))]
+use std::path::{Component, Path};
+use std::sync::Mutex;
+
+use debugid::DebugId;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::decoder::decode_slice;
+use crate::detector::locate_sourcemap_reference_slice;
+#[cfg(any(unix, windows, target_os = "redox"))]
+use crate::discovery::{DiscoveredMap, Discovery};
+use crate::errors::{Error, Result};
+use crate::sourceview::SourceView;
+use crate::types::DecodedMap;
+
+const MANIFEST_PATH: &str = "manifest.json";
+
+/// The type of a file in an artifact bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtifactType {
+    /// A minified JavaScript or CSS file
+    MinifiedSource,
+    /// A sourcemap
+    SourceMap,
+    /// An original source file
+    Source,
+}
+
+impl ArtifactType {
+    /// Returns the name of the type in the manifest.
+    fn name(self) -> &'static str {
+        match self {
+            ArtifactType::MinifiedSource => "minified_source",
+            ArtifactType::SourceMap => "source_map",
+            ArtifactType::Source => "source",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<ArtifactType> {
+        match name {
+            "minified_source" => Some(ArtifactType::MinifiedSource),
+            "source_map" => Some(ArtifactType::SourceMap),
+            "source" => Some(ArtifactType::Source),
+            _ => None,
+        }
+    }
+}
+
+/// Describes a file in an artifact bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactEntry {
+    path: String,
+    url: String,
+    ty: ArtifactType,
+    debug_id: Option<DebugId>,
+    sourcemap: Option<String>,
+}
+
+impl ArtifactEntry {
+    /// Returns the path of the file in the archive.
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the URL of the file.
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the type of the file.
+    pub fn get_type(&self) -> ArtifactType {
+        self.ty
+    }
+
+    /// Returns the debug ID of the file in case there is one.
+    pub fn get_debug_id(&self) -> Option<DebugId> {
+        self.debug_id
+    }
+
+    /// Returns the URL of the sourcemap of a minified file.
+    pub fn get_sourcemap_url(&self) -> Option<&str> {
+        self.sourcemap.as_deref()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawManifestEntry {
+    url: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    debug_id: Option<DebugId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sourcemap: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct RawManifest {
+    files: BTreeMap<String, RawManifestEntry>,
+}
+
+/// Converts a URL into a path in the archive.
+///
+/// Query strings and fragments are dropped and the path is normalized so
+/// that it can never point outside of the `files/` directory.
+fn url_to_path(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let (prefix, rest) = match url.strip_prefix("~/") {
+        Some(rest) => ("_/_".to_string(), rest),
+        None => match url.split_once("://") {
+            Some((scheme, rest)) => (scheme.to_string(), rest),
+            None => ("_".to_string(), url),
+        },
+    };
+
+    let mut segments = vec![];
+    for segment in rest.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut rv = format!("files/{prefix}");
+    for segment in segments {
+        rv.push('/');
+        rv.push_str(segment);
+    }
+    rv
+}
+
+/// Returns the host independent `~/` form of a URL.
+fn tilde_url(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    url.host_str()?;
+    let mut rv = format!("~{}", url.path());
+    if let Some(query) = url.query() {
+        rv.push('?');
+        rv.push_str(query);
+    }
+    Some(rv)
+}
+
+/// Writes an artifact bundle.
+///
+/// The manifest is written by `finish`.
+pub struct ArtifactBundleWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    manifest: RawManifest,
+    // the path and a hash of the contents of every added URL
+    added: HashMap<String, (String, u64)>,
+}
+
+impl<W: Write + Seek> ArtifactBundleWriter<W> {
+    /// Creates a new bundle that is written to the given writer.
+    pub fn new(writer: W) -> ArtifactBundleWriter<W> {
+        ArtifactBundleWriter {
+            zip: ZipWriter::new(writer),
+            manifest: RawManifest::default(),
+            added: HashMap::new(),
+        }
+    }
+
+    fn file_options() -> FileOptions {
+        // a fixed timestamp keeps bundles of the same files identical
+        FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(zip::DateTime::default())
+    }
+
+    /// Adds a file to the bundle.
+    ///
+    /// `sourcemap` is the URL of the sourcemap of a minified file.  If the
+    /// same file was added with the same URL before nothing happens, which
+    /// is useful for sourcemaps that are shared by multiple files.  Adding a
+    /// different file (contents, type or debug ID) under a URL that is
+    /// already in the bundle fails with `Error::ConflictingArtifact`.
+    pub fn add_file(
+        &mut self,
+        url: &str,
+        ty: ArtifactType,
+        contents: &[u8],
+        debug_id: Option<DebugId>,
+        sourcemap: Option<&str>,
+    ) -> Result<()> {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let added = match self.added.entry(url.to_owned()) {
+            Entry::Occupied(added) => {
+                let (path, added_hash) = added.get();
+                let entry = &self.manifest.files[path];
+                if *added_hash == hash && entry.ty == ty.name() && entry.debug_id == debug_id {
+                    return Ok(());
+                }
+                return Err(Error::ConflictingArtifact(url.to_owned()));
+            }
+            Entry::Vacant(added) => added,
+        };
+
+        let mut path = url_to_path(url);
+        let mut counter = 1;
+        while self.manifest.files.contains_key(&path) {
+            counter += 1;
+            path = format!("{}.{counter}", url_to_path(url));
+        }
+
+        self.zip.start_file(path.as_str(), Self::file_options())?;
+        self.zip.write_all(contents)?;
+        added.insert((path.clone(), hash));
+        self.manifest.files.insert(
+            path,
+            RawManifestEntry {
+                url: url.to_owned(),
+                ty: ty.name().to_owned(),
+                debug_id,
+                sourcemap: sourcemap.map(str::to_owned),
+            },
+        );
+        Ok(())
+    }
+
+    /// Adds all paired files of a discovery to the bundle.
+    ///
+    /// URLs are made from `url_prefix` (for instance `~/`) and the path of a
+    /// file relative to `root`.  Sourcemaps outside of `root` are added by
+    /// their absolute path.  Unpaired files and orphan sourcemaps are
+    /// skipped.
+    #[cfg(any(unix, windows, target_os = "redox"))]
+    pub fn add_discovery(
+        &mut self,
+        discovery: &Discovery,
+        root: &Path,
+        url_prefix: &str,
+    ) -> Result<()> {
+        let root = std::fs::canonicalize(root)?;
+        let make_url = |path: &Path| {
+            let rel = path.strip_prefix(&root).unwrap_or(path);
+            let rel: Vec<_> = rel
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(c) => Some(c.to_string_lossy()),
+                    _ => None,
+                })
+                .collect();
+            format!("{}{}", url_prefix, rel.join("/"))
+        };
+
+        for pair in discovery.pairs() {
+            let minified_url = make_url(pair.get_minified_path());
+            let sourcemap_url = match pair.get_sourcemap() {
+                DiscoveredMap::Path(path) => {
+                    let url = make_url(path);
+                    let contents = std::fs::read(path)?;
+                    self.add_file(
+                        &url,
+                        ArtifactType::SourceMap,
+                        &contents,
+                        pair.get_debug_id(),
+                        None,
+                    )?;
+                    Some(url)
+                }
+                DiscoveredMap::Embedded(_) => None,
+            };
+            let contents = std::fs::read(pair.get_minified_path())?;
+            self.add_file(
+                &minified_url,
+                ArtifactType::MinifiedSource,
+                &contents,
+                pair.get_debug_id(),
+                sourcemap_url.as_deref(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the manifest and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.zip.start_file(MANIFEST_PATH, Self::file_options())?;
+        serde_json::to_writer_pretty(&mut self.zip, &self.manifest)?;
+        Ok(self.zip.finish()?)
+    }
+}
+
+/// An artifact bundle opened for lookups.
+pub struct ArtifactBundle<R: Read + Seek> {
+    archive: Mutex<ZipArchive<R>>,
+    entries: Vec<ArtifactEntry>,
+}
+
+impl<R: Read + Seek> ArtifactBundle<R> {
+    /// Opens a bundle and reads its manifest.
+    ///
+    /// Files of types this library does not know are skipped.
+    pub fn new(reader: R) -> Result<ArtifactBundle<R>> {
+        let mut archive = ZipArchive::new(reader)?;
+        let manifest: RawManifest = serde_json::from_reader(archive.by_name(MANIFEST_PATH)?)?;
+        let entries = manifest
+            .files
+            .into_iter()
+            .filter_map(|(path, entry)| {
+                Some(ArtifactEntry {
+                    path,
+                    url: entry.url,
+                    ty: ArtifactType::from_name(&entry.ty)?,
+                    debug_id: entry.debug_id,
+                    sourcemap: entry.sourcemap,
+                })
+            })
+            .collect();
+        Ok(ArtifactBundle {
+            archive: Mutex::new(archive),
+            entries,
+        })
+    }
+
+    /// Returns all files in the bundle.
+    pub fn entries(&self) -> &[ArtifactEntry] {
+        &self.entries
+    }
+
+    /// Looks up a file by its URL.
+    ///
+    /// If there is no exact match a `~/` entry with the same path matches.
+    pub fn get_entry_by_url(&self, url: &str) -> Option<&ArtifactEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.url == url)
+            .or_else(|| {
+                let url = tilde_url(url)?;
+                self.entries.iter().find(|entry| entry.url == url)
+            })
+    }
+
+    /// Looks up a file of the given type by its debug ID.
+    pub fn get_entry_by_debug_id(
+        &self,
+        debug_id: DebugId,
+        ty: ArtifactType,
+    ) -> Option<&ArtifactEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.debug_id == Some(debug_id) && entry.ty == ty)
+    }
+
+    /// Reads the contents of a file.
+    pub fn read_file(&self, entry: &ArtifactEntry) -> Result<Vec<u8>> {
+        let mut archive = self.archive.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = archive.by_name(&entry.path)?;
+        // the size in the header is untrusted, let the buffer grow instead
+        let mut rv = Vec::new();
+        file.read_to_end(&mut rv)?;
+        Ok(rv)
+    }
+
+    fn read_source_view(&self, entry: &ArtifactEntry) -> Result<SourceView<'static>> {
+        Ok(SourceView::from_string(String::from_utf8(
+            self.read_file(entry)?,
+        )?))
+    }
+
+    fn read_sourcemap(&self, entry: &ArtifactEntry) -> Result<Option<DecodedMap>> {
+        let entry = match entry.ty {
+            ArtifactType::SourceMap => entry,
+            ArtifactType::MinifiedSource => match entry
+                .sourcemap
+                .as_deref()
+                .and_then(|url| self.get_entry_by_url(url))
+            {
+                Some(entry) => entry,
+                // the sourcemap might be embedded as a data URL
+                None => {
+                    return match locate_sourcemap_reference_slice(&self.read_file(entry)?)? {
+                        Some(reference) => reference.get_embedded_sourcemap(),
+                        None => Ok(None),
+                    }
+                }
+            },
+            ArtifactType::Source => return Ok(None),
+        };
+        decode_slice(&self.read_file(entry)?).map(Some)
+    }
+
+    /// Reads the file with the given URL as source view.
+    pub fn get_source_view_by_url(&self, url: &str) -> Result<Option<SourceView<'static>>> {
+        self.get_entry_by_url(url)
+            .map(|entry| self.read_source_view(entry))
+            .transpose()
+    }
+
+    /// Reads and decodes a sourcemap by URL.
+    ///
+    /// The URL can either be the one of the sourcemap or of the minified file
+    /// it belongs to.  Sourcemaps embedded into a minified file as data URL
+    /// are decoded from the minified file.
+    pub fn get_sourcemap_by_url(&self, url: &str) -> Result<Option<DecodedMap>> {
+        match self.get_entry_by_url(url) {
+            Some(entry) => self.read_sourcemap(entry),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the minified file with the given debug ID as source view.
+    pub fn get_source_view_by_debug_id(
+        &self,
+        debug_id: DebugId,
+    ) -> Result<Option<SourceView<'static>>> {
+        self.get_entry_by_debug_id(debug_id, ArtifactType::MinifiedSource)
+            .map(|entry| self.read_source_view(entry))
+            .transpose()
+    }
+
+    /// Reads and decodes the sourcemap with the given debug ID.
+    ///
+    /// If there is no sourcemap with the debug ID the one of the minified file
+    /// with the debug ID is used.
+    pub fn get_sourcemap_by_debug_id(&self, debug_id: DebugId) -> Result<Option<DecodedMap>> {
+        match self
+            .get_entry_by_debug_id(debug_id, ArtifactType::SourceMap)
+            .or_else(|| self.get_entry_by_debug_id(debug_id, ArtifactType::MinifiedSource))
+        {
+            Some(entry) => self.read_sourcemap(entry),
+            None => Ok(None),
+        }
+    }
+}
+
+#[test]
+fn test_url_to_path() {
+    assert_eq!(url_to_path("~/app.js"), "files/_/_/app.js");
+    assert_eq!(
+        url_to_path("https://example.com/static/app.js"),
+        "files/https/example.com/static/app.js"
+    );
+    assert_eq!(url_to_path("/app.js"), "files/_/app.js");
+    assert_eq!(
+        url_to_path("https://example.com/static/app.js?v=1#top"),
+        "files/https/example.com/static/app.js"
+    );
+    assert_eq!(url_to_path("~/a//./b/app.js"), "files/_/_/a/b/app.js");
+    assert_eq!(url_to_path("~/../../../etc/passwd"), "files/_/_/etc/passwd");
+    assert_eq!(url_to_path("/a/..\\..\\app.js"), "files/_/app.js");
+    assert_eq!(url_to_path("../../app.js"), "files/_/app.js");
+}
+
+#[test]
+fn test_tilde_url() {
+    assert_eq!(
+        tilde_url("https://example.com/static/app.js?v=1").as_deref(),
+        Some("~/static/app.js?v=1")
+    );
+    assert_eq!(tilde_url("~/app.js"), None);
+}
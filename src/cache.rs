@@ -0,0 +1,655 @@
+//! A compact binary format for fast repeated symbolication.
+//!
+//! Parsing a large sourcemap from JSON is expensive.  A `SourceMapCache` holds
+//! everything that is needed to resolve minified positions in a binary format
+//! that can be used directly from a byte slice (for instance a memory mapped
+//! file) without any upfront parsing:
+//!
+//! ```rust
+//! use sourcemap::cache::{SourceMapCache, SourceMapCacheWriter};
+//! use sourcemap::DecodedMap;
+//! let input: &[_] = b"{
+//!     \"version\":3,
+//!     \"sources\":[\"coolstuff.js\"],
+//!     \"names\":[\"x\",\"alert\"],
+//!     \"mappings\":\"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM\"
+//! }";
+//! let sm = DecodedMap::from_reader(input).unwrap();
+//!
+//! let mut buf = vec![];
+//! SourceMapCacheWriter::new(&sm, None)
+//!     .unwrap()
+//!     .serialize(&mut buf)
+//!     .unwrap();
+//!
+//! let cache = SourceMapCache::parse(&buf).unwrap();
+//! let location = cache.lookup(0, 3).unwrap();
+//! assert_eq!(location.get_source(), Some("coolstuff.js"));
+//! assert_eq!(location.get_name(), Some("x"));
+//! ```
+//!
+//! # Format
+//!
+//! All integers are little endian `u32`s and `!0` marks a missing reference.
+//! The file starts with a header (magic `SMCA`, format version, token count,
+//! file count, line offset count, string count, string data size) followed
+//! by these tables:
+//!
+//! * tokens sorted by generated position: generated line and column,
+//!   original line and column, file, name and function name
+//! * files: name, source contents and the range of their line offsets
+//! * line offsets of all files with source contents
+//! * strings as offset and length into the string data
+//! * the string data
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::io::Write;
+use std::str;
+
+use crate::errors::{Error, Result};
+use crate::sourceview::SourceView;
+use crate::types::{DecodedMap, SourceMap, Token};
+
+/// Magic number for sourcemap caches
+pub const CACHE_MAGIC: [u8; 4] = *b"SMCA";
+
+/// The format version written by this library
+pub const CACHE_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 28;
+const TOKEN_SIZE: usize = 28;
+const FILE_SIZE: usize = 16;
+const STRING_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct CacheToken {
+    dst_line: u32,
+    dst_col: u32,
+    src_line: u32,
+    src_col: u32,
+    file: u32,
+    name: u32,
+    function: u32,
+}
+
+#[derive(Debug, Clone)]
+struct CacheFile {
+    name: u32,
+    contents: Option<String>,
+}
+
+/// Converts sourcemaps into the binary cache format.
+#[derive(Debug, Clone)]
+pub struct SourceMapCacheWriter {
+    tokens: Vec<CacheToken>,
+    files: Vec<CacheFile>,
+    strings: Vec<String>,
+    string_ids: HashMap<String, u32>,
+    with_source_contents: bool,
+}
+
+fn get_line_offsets(source: &str) -> Vec<u32> {
+    let bytes = source.as_bytes();
+    let mut rv = vec![0];
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'\r' && bytes.get(idx + 1) == Some(&b'\n') {
+            idx += 1;
+        }
+        if bytes[idx] == b'\n' || bytes[idx] == b'\r' {
+            rv.push(idx as u32 + 1);
+        }
+        idx += 1;
+    }
+    rv
+}
+
+/// A function in the minified source.
+struct Scope<'a> {
+    name: Option<&'a str>,
+    /// The brace depth outside of the function.
+    depth: u32,
+    /// The paren depth outside of the parameter list.
+    parens: u32,
+    params_done: bool,
+    open: bool,
+}
+
+fn is_ident_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c >= 0x80
+}
+
+/// Returns the end of the identifier starting at `pos`.
+fn skip_ident(source: &[u8], pos: usize) -> usize {
+    source[pos..]
+        .iter()
+        .position(|&c| !is_ident_byte(c))
+        .map_or(source.len(), |len| pos + len)
+}
+
+/// Returns the end of the string literal or comment starting at `pos`.
+fn skip_literal(source: &[u8], pos: usize) -> usize {
+    let find = |from: usize, needle: &[u8]| {
+        source[from.min(source.len())..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map_or(source.len(), |idx| from + idx + needle.len())
+    };
+    match (source[pos], source.get(pos + 1)) {
+        (b'/', Some(b'/')) => find(pos + 2, b"\n"),
+        (b'/', Some(b'*')) => find(pos + 2, b"*/"),
+        (quote, _) => {
+            let mut idx = pos + 1;
+            while idx < source.len() {
+                match source[idx] {
+                    b'\\' => idx += 2,
+                    c if c == quote => return idx + 1,
+                    b'\n' if quote != b'`' => return idx + 1,
+                    _ => idx += 1,
+                }
+            }
+            source.len()
+        }
+    }
+}
+
+/// Resolves the function every token is in from the `function`
+/// declarations in the minified source.
+///
+/// Function bodies are found by matching braces so that code after a
+/// function does not get its name.  Code in functions without a name in
+/// the sourcemap gets no name either.  The result is indexed by raw token
+/// index.
+///
+/// Like `SourceView::get_original_function_name` only `function`
+/// declarations and expressions are understood, the name is the one the
+/// sourcemap has for the minified name after `function`.  Arrow functions,
+/// object methods and class members get no name.  Unlike the lookup for a
+/// single frame the scan is not limited to a number of tokens before the
+/// position, so tokens in large functions get a name here even if
+/// `get_original_function_name` gives up.
+fn resolve_function_names<'a>(sm: &'a SourceMap, sv: &SourceView<'_>) -> Vec<Option<&'a str>> {
+    let mut rv = vec![None; sm.get_token_count() as usize];
+    let mut tokens = vec![];
+    let mut names = BTreeMap::new();
+    for (line, col, idx) in sm.index_iter() {
+        if let Some(offset) = sv.position_to_offset(line, col) {
+            tokens.push((offset, idx));
+            if let Some(name) = sm.get_token(idx).and_then(|token| token.get_name()) {
+                names.entry(offset).or_insert(name);
+            }
+        }
+    }
+
+    let source = sv.source().as_bytes();
+    let mut tokens = tokens.into_iter().peekable();
+    let mut scopes: Vec<Scope<'a>> = vec![];
+    let mut depth = 0u32;
+    let mut parens = 0u32;
+    let mut pos = 0;
+
+    loop {
+        while let Some(&(offset, idx)) = tokens.peek() {
+            if offset > pos && pos < source.len() {
+                break;
+            }
+            tokens.next();
+            rv[idx as usize] = scopes.iter().rev().find(|s| s.open).and_then(|s| s.name);
+        }
+        if pos >= source.len() {
+            break;
+        }
+
+        match source[pos] {
+            b'"' | b'\'' | b'`' => pos = skip_literal(source, pos),
+            b'/' if matches!(source.get(pos + 1), Some(b'/') | Some(b'*')) => {
+                pos = skip_literal(source, pos)
+            }
+            b'(' => {
+                parens += 1;
+                pos += 1;
+            }
+            b')' => {
+                parens = parens.saturating_sub(1);
+                if let Some(scope) = scopes.last_mut() {
+                    if !scope.open && scope.parens == parens {
+                        scope.params_done = true;
+                    }
+                }
+                pos += 1;
+            }
+            b'{' => {
+                if let Some(scope) = scopes.last_mut() {
+                    if !scope.open && scope.params_done {
+                        scope.open = true;
+                    }
+                }
+                depth += 1;
+                pos += 1;
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                // braces in parameter lists do not close their function
+                while scopes.last().is_some_and(|scope| {
+                    scope.depth > depth || (scope.open && scope.depth == depth)
+                }) {
+                    scopes.pop();
+                }
+                pos += 1;
+            }
+            c if is_ident_byte(c) && (pos == 0 || !is_ident_byte(source[pos - 1])) => {
+                let end = skip_ident(source, pos);
+                if &source[pos..end] == b"function" {
+                    let mut name_start = end;
+                    while name_start < source.len()
+                        && (source[name_start].is_ascii_whitespace() || source[name_start] == b'*')
+                    {
+                        name_start += 1;
+                    }
+                    let has_name = name_start < source.len() && is_ident_byte(source[name_start]);
+                    scopes.push(Scope {
+                        // some minifiers map the name from the whitespace before it
+                        name: names
+                            .range(end..=name_start)
+                            .next_back()
+                            .map(|(_, &name)| name)
+                            .filter(|_| has_name),
+                        depth,
+                        parens,
+                        params_done: false,
+                        open: false,
+                    });
+                }
+                pos = end;
+            }
+            _ => pos += 1,
+        }
+    }
+
+    rv
+}
+
+impl SourceMapCacheWriter {
+    /// Prepares the conversion of a decoded sourcemap.
+    ///
+    /// Indexed sourcemaps are flattened.  Function names come from the scope
+    /// information of hermes sourcemaps.  For other sourcemaps they are
+    /// guessed from `function` declarations in the given minified source,
+    /// if any, the same way `SourceMap::get_original_function_name` does.
+    /// Arrow functions, methods and class members get no function name.
+    pub fn new(sm: &DecodedMap, minified: Option<&SourceView<'_>>) -> Result<SourceMapCacheWriter> {
+        let mut rv = SourceMapCacheWriter {
+            tokens: vec![],
+            files: vec![],
+            strings: vec![],
+            string_ids: HashMap::new(),
+            with_source_contents: false,
+        };
+
+        match *sm {
+            DecodedMap::Regular(ref sm) => {
+                let functions = minified.map(|sv| resolve_function_names(sm, sv));
+                rv.add_sourcemap(sm, |idx, _| {
+                    functions.as_ref().and_then(|f| f[idx as usize])
+                });
+            }
+            DecodedMap::Index(ref smi) => {
+                let sm = smi.flatten()?;
+                let functions = minified.map(|sv| resolve_function_names(&sm, sv));
+                rv.add_sourcemap(&sm, |idx, _| {
+                    functions.as_ref().and_then(|f| f[idx as usize])
+                });
+            }
+            DecodedMap::Hermes(ref smh) => {
+                rv.add_sourcemap(smh, |_, token| smh.get_scope_for_token(token));
+            }
+        }
+
+        Ok(rv)
+    }
+
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&id) = self.string_ids.get(s) {
+            return id;
+        }
+        let id = self.strings.len() as u32;
+        self.strings.push(s.to_string());
+        self.string_ids.insert(s.to_string(), id);
+        id
+    }
+
+    fn add_sourcemap<'a, F>(&mut self, sm: &'a SourceMap, get_function: F)
+    where
+        F: Fn(u32, Token<'a>) -> Option<&'a str>,
+    {
+        for idx in 0..sm.get_source_count() {
+            let name = self.intern(sm.get_source(idx).unwrap_or_default());
+            self.files.push(CacheFile {
+                name,
+                contents: sm.get_source_contents(idx).map(str::to_string),
+            });
+        }
+
+        for (_, _, idx) in sm.index_iter() {
+            let token = match sm.get_token(idx) {
+                Some(token) => token,
+                None => continue,
+            };
+            let name = token.get_name().map_or(!0, |name| self.intern(name));
+            let function = get_function(idx, token).map_or(!0, |name| self.intern(name));
+            self.tokens.push(CacheToken {
+                dst_line: token.get_dst_line(),
+                dst_col: token.get_dst_col(),
+                src_line: token.get_src_line(),
+                src_col: token.get_src_col(),
+                file: if token.get_source().is_some() {
+                    token.get_src_id()
+                } else {
+                    !0
+                },
+                name,
+                function,
+            });
+        }
+    }
+
+    /// Sets whether the source contents are included in the cache.
+    ///
+    /// This makes the original source lines available through
+    /// `CacheLocation::get_source_line` at the cost of a larger cache.
+    pub fn set_with_source_contents(&mut self, value: bool) {
+        self.with_source_contents = value;
+    }
+
+    /// Serializes the cache into a writer.
+    pub fn serialize<W: Write>(&self, mut w: W) -> Result<()> {
+        let mut strings: Vec<&str> = self.strings.iter().map(String::as_str).collect();
+        let mut files = Vec::with_capacity(self.files.len());
+        let mut line_offsets = vec![];
+        for file in &self.files {
+            let contents = file
+                .contents
+                .as_deref()
+                .filter(|_| self.with_source_contents);
+            let (source, first, count) = match contents {
+                Some(contents) => {
+                    let first = line_offsets.len();
+                    line_offsets.extend(get_line_offsets(contents));
+                    strings.push(contents);
+                    (strings.len() - 1, first, line_offsets.len() - first)
+                }
+                None => (!0, 0, 0),
+            };
+            files.push([file.name, source as u32, first as u32, count as u32]);
+        }
+
+        let string_bytes: usize = strings.iter().map(|s| s.len()).sum();
+        if line_offsets.len() > u32::MAX as usize || string_bytes > u32::MAX as usize {
+            return Err(Error::InvalidCacheData);
+        }
+
+        let mut buf = Vec::with_capacity(
+            HEADER_SIZE
+                + self.tokens.len() * TOKEN_SIZE
+                + files.len() * FILE_SIZE
+                + line_offsets.len() * 4
+                + strings.len() * STRING_SIZE
+                + string_bytes,
+        );
+        let push = |buf: &mut Vec<u8>, value: u32| buf.extend_from_slice(&value.to_le_bytes());
+
+        buf.extend_from_slice(&CACHE_MAGIC);
+        push(&mut buf, CACHE_VERSION);
+        push(&mut buf, self.tokens.len() as u32);
+        push(&mut buf, files.len() as u32);
+        push(&mut buf, line_offsets.len() as u32);
+        push(&mut buf, strings.len() as u32);
+        push(&mut buf, string_bytes as u32);
+
+        for token in &self.tokens {
+            push(&mut buf, token.dst_line);
+            push(&mut buf, token.dst_col);
+            push(&mut buf, token.src_line);
+            push(&mut buf, token.src_col);
+            push(&mut buf, token.file);
+            push(&mut buf, token.name);
+            push(&mut buf, token.function);
+        }
+        for file in &files {
+            for &value in file {
+                push(&mut buf, value);
+            }
+        }
+        for &offset in &line_offsets {
+            push(&mut buf, offset);
+        }
+
+        let mut offset = 0;
+        for string in &strings {
+            push(&mut buf, offset);
+            push(&mut buf, string.len() as u32);
+            offset += string.len() as u32;
+        }
+        for string in &strings {
+            buf.extend_from_slice(string.as_bytes());
+        }
+
+        w.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// A sourcemap cache that is read directly from a byte slice.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceMapCache<'a> {
+    tokens: &'a [u8],
+    files: &'a [u8],
+    line_offsets: &'a [u8],
+    strings: &'a [u8],
+    string_data: &'a [u8],
+}
+
+/// A resolved location from a `SourceMapCache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLocation<'a> {
+    cache: SourceMapCache<'a>,
+    dst_line: u32,
+    dst_col: u32,
+    src_line: u32,
+    src_col: u32,
+    file: u32,
+    name: u32,
+    function: u32,
+}
+
+impl<'a> PartialEq for SourceMapCache<'a> {
+    fn eq(&self, other: &SourceMapCache<'_>) -> bool {
+        std::ptr::eq(self.tokens, other.tokens)
+    }
+}
+
+impl<'a> Eq for SourceMapCache<'a> {}
+
+impl<'a> SourceMapCache<'a> {
+    /// Opens a sourcemap cache from a byte slice.
+    ///
+    /// This only validates the header and the table sizes, no data is copied.
+    pub fn parse(data: &'a [u8]) -> Result<SourceMapCache<'a>> {
+        if data.len() < HEADER_SIZE {
+            return Err(Error::InvalidCacheData);
+        }
+        if data[..4] != CACHE_MAGIC {
+            return Err(Error::InvalidCacheMagic);
+        }
+        let version = read_u32(data, 4);
+        if version != CACHE_VERSION {
+            return Err(Error::UnsupportedCacheVersion(version));
+        }
+
+        let mut offset = HEADER_SIZE;
+        let mut take = |count: u32, size: usize| -> Result<&'a [u8]> {
+            let len = (count as usize)
+                .checked_mul(size)
+                .ok_or(Error::InvalidCacheData)?;
+            let end = offset.checked_add(len).ok_or(Error::InvalidCacheData)?;
+            let rv = data.get(offset..end).ok_or(Error::InvalidCacheData)?;
+            offset = end;
+            Ok(rv)
+        };
+
+        Ok(SourceMapCache {
+            tokens: take(read_u32(data, 8), TOKEN_SIZE)?,
+            files: take(read_u32(data, 12), FILE_SIZE)?,
+            line_offsets: take(read_u32(data, 16), 4)?,
+            strings: take(read_u32(data, 20), STRING_SIZE)?,
+            string_data: take(read_u32(data, 24), 1)?,
+        })
+    }
+
+    /// Returns the number of tokens in the cache.
+    pub fn get_token_count(&self) -> u32 {
+        (self.tokens.len() / TOKEN_SIZE) as u32
+    }
+
+    /// Returns the number of source files in the cache.
+    pub fn get_file_count(&self) -> u32 {
+        (self.files.len() / FILE_SIZE) as u32
+    }
+
+    fn get_string(&self, idx: u32) -> Option<&'a str> {
+        let offset = idx as usize * STRING_SIZE;
+        if idx == !0 || offset >= self.strings.len() {
+            return None;
+        }
+        let start = read_u32(self.strings, offset) as usize;
+        let len = read_u32(self.strings, offset + 4) as usize;
+        let bytes = self.string_data.get(start..start.checked_add(len)?)?;
+        str::from_utf8(bytes).ok()
+    }
+
+    fn get_dst(&self, idx: usize) -> (u32, u32) {
+        let offset = idx * TOKEN_SIZE;
+        (
+            read_u32(self.tokens, offset),
+            read_u32(self.tokens, offset + 4),
+        )
+    }
+
+    fn get_location(&self, idx: usize) -> CacheLocation<'a> {
+        let offset = idx * TOKEN_SIZE;
+        let field = |n: usize| read_u32(self.tokens, offset + n * 4);
+        CacheLocation {
+            cache: *self,
+            dst_line: field(0),
+            dst_col: field(1),
+            src_line: field(2),
+            src_col: field(3),
+            file: field(4),
+            name: field(5),
+            function: field(6),
+        }
+    }
+
+    /// Looks up the closest location to a given 0-indexed line and column.
+    ///
+    /// This resolves the same token as `SourceMap::lookup_token`.
+    pub fn lookup(&self, line: u32, col: u32) -> Option<CacheLocation<'a>> {
+        let count = self.get_token_count() as usize;
+        let key = (line, col);
+
+        // find the first token that is not smaller than the key
+        let (mut lo, mut hi) = (0, count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.get_dst(mid) < key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        if lo < count && self.get_dst(lo) == key {
+            Some(self.get_location(lo))
+        } else {
+            Some(self.get_location(lo.checked_sub(1)?))
+        }
+    }
+}
+
+impl<'a> CacheLocation<'a> {
+    /// get the destination (minified) line number
+    pub fn get_dst_line(&self) -> u32 {
+        self.dst_line
+    }
+
+    /// get the destination (minified) column number
+    pub fn get_dst_col(&self) -> u32 {
+        self.dst_col
+    }
+
+    /// get the source line number
+    pub fn get_src_line(&self) -> u32 {
+        self.src_line
+    }
+
+    /// get the source column number
+    pub fn get_src_col(&self) -> u32 {
+        self.src_col
+    }
+
+    fn get_file(&self) -> Option<usize> {
+        let offset = self.file as usize * FILE_SIZE;
+        if self.file == !0 || offset >= self.cache.files.len() {
+            None
+        } else {
+            Some(offset)
+        }
+    }
+
+    /// get the source if it exists as string
+    pub fn get_source(&self) -> Option<&'a str> {
+        let offset = self.get_file()?;
+        self.cache.get_string(read_u32(self.cache.files, offset))
+    }
+
+    /// get the name if it exists as string
+    pub fn get_name(&self) -> Option<&'a str> {
+        self.cache.get_string(self.name)
+    }
+
+    /// get the original name of the enclosing function if it is known
+    pub fn get_function_name(&self) -> Option<&'a str> {
+        self.cache.get_string(self.function)
+    }
+
+    /// Returns the original source line if source contents were included.
+    pub fn get_source_line(&self) -> Option<&'a str> {
+        let cache = &self.cache;
+        let offset = self.get_file()?;
+        let source = cache.get_string(read_u32(cache.files, offset + 4))?;
+        let first = read_u32(cache.files, offset + 8) as usize;
+        let count = read_u32(cache.files, offset + 12) as usize;
+        if self.src_line as usize >= count {
+            return None;
+        }
+
+        let line_offset = |idx: usize| -> Option<usize> {
+            let pos = (first + idx) * 4;
+            cache
+                .line_offsets
+                .get(pos..pos + 4)
+                .map(|_| read_u32(cache.line_offsets, pos) as usize)
+        };
+        let start = line_offset(self.src_line as usize)?;
+        let end = if (self.src_line as usize) + 1 < count {
+            line_offset(self.src_line as usize + 1)?
+        } else {
+            source.len()
+        };
+        Some(source.get(start..end)?.trim_end_matches(&['\n', '\r'][..]))
+    }
+}
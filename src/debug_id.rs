@@ -0,0 +1,243 @@
+//! Stamps minified files and their sourcemaps with debug IDs.
+//!
+//! A debug ID ties a minified file to its sourcemap independent of where
+//! either of them is hosted.  The file carries it in a `//# debugId=`
+//! comment and the sourcemap in its `debugId` field:
+//!
+//! ```rust
+//! use sourcemap::debug_id::{inject_debug_id, InjectOptions};
+//! use sourcemap::{DecodedMap, SourceView};
+//! let input: &[_] = b"{
+//!     \"version\":3,
+//!     \"sources\":[\"coolstuff.js\"],
+//!     \"names\":[\"x\",\"alert\"],
+//!     \"mappings\":\"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM\"
+//! }";
+//! let mut sm = DecodedMap::from_reader(input).unwrap();
+//! let source = SourceView::new("var x = 1;\nalert(x);\n");
+//!
+//! let injected = inject_debug_id(&source, &mut sm, &InjectOptions::default());
+//! assert_eq!(sm.get_debug_id(), Some(injected.get_debug_id()));
+//! assert!(injected.get_source().contains("//# debugId="));
+//! ```
+//!
+//! The debug ID is derived from the contents of the minified file so the
+//! same build always gets the same ID.  Files that already have a debug ID
+//! comment are left alone.
+use debugid::DebugId;
+use uuid::Uuid;
+
+use crate::detector::{append_line, locate_debug_id, SourceKind};
+use crate::sourceview::SourceView;
+use crate::types::DecodedMap;
+
+/// The namespace of the name based UUIDs that debug IDs are made from.
+const DEBUG_ID_NAMESPACE: Uuid = Uuid::from_u128(0x0d1f_77f6_2c4a_4e0b_a5a9_5c4d_d3b2_1e8a);
+
+/// Controls how debug IDs are injected.
+#[derive(Debug, Clone, Copy)]
+pub struct InjectOptions {
+    /// If enabled a snippet is added to JavaScript files which registers the
+    /// debug ID in the global `_debugIds` object at runtime.  There is no
+    /// such snippet for CSS, so this is ignored if `kind` is
+    /// `SourceKind::Css`.
+    pub runtime_snippet: bool,
+    /// The syntax of the debug ID comment.
+    pub kind: SourceKind,
+}
+
+impl Default for InjectOptions {
+    /// Default configuration:
+    ///
+    /// - `runtime_snippet`: false
+    /// - `kind`: `SourceKind::JavaScript`
+    fn default() -> InjectOptions {
+        InjectOptions {
+            runtime_snippet: false,
+            kind: SourceKind::JavaScript,
+        }
+    }
+}
+
+/// A minified file after debug ID injection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedSource {
+    source: String,
+    debug_id: DebugId,
+    modified: bool,
+}
+
+impl InjectedSource {
+    /// Returns the new contents of the minified file.
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    /// Converts into the new contents of the minified file.
+    pub fn into_source(self) -> String {
+        self.source
+    }
+
+    /// Returns the debug ID of the file.
+    pub fn get_debug_id(&self) -> DebugId {
+        self.debug_id
+    }
+
+    /// Returns `true` if the file was changed.
+    ///
+    /// This is `false` if the file already had a debug ID.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+}
+
+/// Derives a debug ID from the contents of a minified file.
+pub fn debug_id_from_source(source: &str) -> DebugId {
+    DebugId::from_uuid(Uuid::new_v5(&DEBUG_ID_NAMESPACE, source.as_bytes()))
+}
+
+fn runtime_snippet(debug_id: DebugId) -> String {
+    format!(
+        "!function(){{try{{var e=\"undefined\"!=typeof window?window:\"undefined\"!=typeof global?\
+         global:\"undefined\"!=typeof globalThis?globalThis:\"undefined\"!=typeof self?self:{{}},\
+         n=(new e.Error).stack;n&&(e._debugIds=e._debugIds||{{}},e._debugIds[n]=\"{debug_id}\")\
+         }}catch(e){{}}}}();"
+    )
+}
+
+/// Checks if a line only holds a directive such as `"use strict";`.
+fn is_directive_line(line: &str) -> bool {
+    let line = line.trim();
+    let line = line.strip_suffix(';').unwrap_or(line).trim_end();
+    match line.as_bytes().first() {
+        Some(&quote) if quote == b'"' || quote == b'\'' => {
+            line.len() >= 2
+                && line.as_bytes()[line.len() - 1] == quote
+                && !line[1..line.len() - 1].contains(quote as char)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the line that the runtime snippet goes on.
+///
+/// This skips a hashbang and the directive prologue as both stop working
+/// if code is put before them.  If the prologue shares a line with other
+/// code there is no such line and `None` is returned.
+fn snippet_line(source: &SourceView<'_>) -> Option<u32> {
+    let mut rv = 0;
+    for (idx, line) in source.lines().enumerate() {
+        if (idx == 0 && line.starts_with("#!")) || is_directive_line(line) {
+            rv = idx as u32 + 1;
+        } else if !line.trim().is_empty() {
+            let line = line.trim_start();
+            if line.starts_with("\"use ") || line.starts_with("'use ") {
+                return None;
+            }
+            break;
+        }
+    }
+    Some(rv)
+}
+
+/// Inserts a line before the given line of the source.
+fn insert_line(source: &str, line: u32, text: &str) -> String {
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let offset: usize = source
+        .split_inclusive('\n')
+        .take(line as usize)
+        .map(str::len)
+        .sum();
+
+    let mut rv = String::with_capacity(source.len() + text.len() + 4);
+    rv.push_str(&source[..offset]);
+    if !rv.is_empty() && !rv.ends_with('\n') {
+        rv.push_str(newline);
+    }
+    rv.push_str(text);
+    rv.push_str(newline);
+    rv.push_str(&source[offset..]);
+    rv
+}
+
+/// Injects a debug ID into a minified file and its sourcemap.
+///
+/// The debug ID is derived from the file with `debug_id_from_source`.  A
+/// debug ID comment is appended to the file and the ID is written to the
+/// `debugId` field of the sourcemap.  If the runtime snippet is enabled it
+/// goes on a line of its own at the top of the file (after a hashbang and
+/// the directive prologue) and the sourcemap is moved down by that line so
+/// that all positions stay correct.  If the directive prologue shares a
+/// line with code, or the sourcemap is an index with a section before that
+/// line which only references its sourcemap by URL and thus cannot be
+/// moved, the snippet is appended instead.
+///
+/// If the file already has a debug ID comment it is not changed again and
+/// only the sourcemap gets that ID.  Injecting twice thus has no effect.
+pub fn inject_debug_id(
+    source: &SourceView<'_>,
+    sm: &mut DecodedMap,
+    options: &InjectOptions,
+) -> InjectedSource {
+    if let Some(debug_id) = locate_debug_id(source.source().as_bytes()) {
+        sm.set_debug_id(Some(debug_id));
+        return InjectedSource {
+            source: source.source().to_owned(),
+            debug_id,
+            modified: false,
+        };
+    }
+
+    let debug_id = debug_id_from_source(source.source());
+    let comment = options
+        .kind
+        .format_directive("debugId", &debug_id.to_string());
+
+    let rv = if options.runtime_snippet && options.kind == SourceKind::JavaScript {
+        let snippet = runtime_snippet(debug_id);
+        match snippet_line(source).filter(|&line| sm.can_insert_dst_lines(line)) {
+            Some(line) => {
+                sm.insert_dst_lines(line, 1);
+                let body = insert_line(source.source(), line, &snippet);
+                append_line(&body, source.source(), &comment)
+            }
+            None => {
+                // nothing moves if the snippet goes to the end instead
+                let body = append_line(source.source(), source.source(), &snippet);
+                append_line(&body, source.source(), &comment)
+            }
+        }
+    } else {
+        append_line(source.source(), source.source(), &comment)
+    };
+    sm.set_debug_id(Some(debug_id));
+
+    InjectedSource {
+        source: rv,
+        debug_id,
+        modified: true,
+    }
+}
+
+#[test]
+fn test_is_directive_line() {
+    assert!(is_directive_line("\"use strict\";"));
+    assert!(is_directive_line("  'use client'"));
+    assert!(!is_directive_line("\"use strict\" + x;"));
+    assert!(!is_directive_line("\"a\" + \"b\";"));
+    assert!(!is_directive_line("foo();"));
+}
+
+#[test]
+fn test_insert_line() {
+    assert_eq!(insert_line("a\nb\n", 0, "x"), "x\na\nb\n");
+    assert_eq!(insert_line("a\r\nb", 1, "x"), "a\r\nx\r\nb");
+    assert_eq!(
+        insert_line("#!/usr/bin/env node", 1, "x"),
+        "#!/usr/bin/env node\nx\n"
+    );
+}
@@ -7,9 +7,9 @@ use crate::errors::{Error, Result};
 use crate::hermes::decode_hermes;
 use crate::jsontypes::RawSourceMap;
 use crate::types::{DecodedMap, RawToken, SourceMap, SourceMapIndex, SourceMapSection};
-use crate::vlq::parse_vlq_segment_into;
+use crate::vlq::MappingsDecoder;
 
-const DATA_PREAMBLE: &str = "data:application/json;base64,";
+pub(crate) const DATA_PREAMBLE: &str = "data:application/json;base64,";
 
 #[derive(PartialEq, Eq)]
 enum HeaderState {
@@ -120,72 +120,43 @@ pub fn strip_junk_header(slice: &[u8]) -> io::Result<&[u8]> {
     Ok(&slice[slice.len()..])
 }
 
-pub fn decode_regular(rsm: RawSourceMap) -> Result<SourceMap> {
-    let mut dst_col;
-    let mut src_id = 0;
-    let mut src_line = 0;
-    let mut src_col = 0;
-    let mut name_id = 0;
+fn decode_mappings(mappings: &str, source_count: u32, name_count: u32) -> Result<Vec<RawToken>> {
+    #[cfg(feature = "rayon")]
+    {
+        if mappings.len() >= crate::parallel::PARALLEL_DECODE_THRESHOLD
+            && rayon::current_num_threads() > 1
+        {
+            return crate::parallel::decode_mappings(mappings, source_count, name_count);
+        }
+    }
 
-    let names = rsm.names.unwrap_or_default();
-    let sources = rsm.sources.unwrap_or_default();
-    let mappings = rsm.mappings.unwrap_or_default();
     let allocation_size = mappings.matches(&[',', ';'][..]).count() + 10;
     let mut tokens = Vec::with_capacity(allocation_size);
 
-    let mut nums = Vec::with_capacity(6);
-
-    for (dst_line, line) in mappings.split(';').enumerate() {
-        if line.is_empty() {
-            continue;
-        }
+    for token in MappingsDecoder::new(mappings).check_references(source_count, name_count) {
+        tokens.push(token?);
+    }
 
-        dst_col = 0;
+    Ok(tokens)
+}
 
-        for segment in line.split(',') {
-            if segment.is_empty() {
-                continue;
-            }
+/// Decodes the mappings of a raw sourcemap and checks its references.
+pub fn decode_raw_mappings(rsm: &RawSourceMap) -> Result<Vec<RawToken>> {
+    decode_mappings(
+        rsm.mappings.as_deref().unwrap_or_default(),
+        rsm.sources.as_ref().map_or(0, Vec::len) as u32,
+        rsm.names.as_ref().map_or(0, Vec::len) as u32,
+    )
+}
 
-            nums.clear();
-            parse_vlq_segment_into(segment, &mut nums)?;
-            dst_col = (i64::from(dst_col) + nums[0]) as u32;
-
-            let mut src = !0;
-            let mut name = !0;
-
-            if nums.len() > 1 {
-                if nums.len() != 4 && nums.len() != 5 {
-                    fail!(Error::BadSegmentSize(nums.len() as u32));
-                }
-                src_id = (i64::from(src_id) + nums[1]) as u32;
-                if src_id >= sources.len() as u32 {
-                    fail!(Error::BadSourceReference(src_id));
-                }
-
-                src = src_id;
-                src_line = (i64::from(src_line) + nums[2]) as u32;
-                src_col = (i64::from(src_col) + nums[3]) as u32;
-
-                if nums.len() > 4 {
-                    name_id = (i64::from(name_id) + nums[4]) as u32;
-                    if name_id >= names.len() as u32 {
-                        fail!(Error::BadNameReference(name_id));
-                    }
-                    name = name_id;
-                }
-            }
+pub fn decode_regular(rsm: RawSourceMap) -> Result<SourceMap> {
+    let tokens = decode_raw_mappings(&rsm)?;
+    Ok(decode_regular_with_tokens(rsm, tokens))
+}
 
-            tokens.push(RawToken {
-                dst_line: dst_line as u32,
-                dst_col,
-                src_line,
-                src_col,
-                src_id: src,
-                name_id: name,
-            });
-        }
-    }
+fn decode_regular_with_tokens(rsm: RawSourceMap, tokens: Vec<RawToken>) -> SourceMap {
+    let names = rsm.names.unwrap_or_default();
+    let sources = rsm.sources.unwrap_or_default();
 
     let sources = match rsm.source_root {
         Some(ref source_root) if !source_root.is_empty() => {
@@ -234,20 +205,35 @@ pub fn decode_regular(rsm: RawSourceMap) -> Result<SourceMap> {
 
     let mut sm = SourceMap::new(file, tokens, names, sources, rsm.sources_content);
     sm.set_source_root(rsm.source_root);
+    sm.set_debug_id(rsm.debug_id);
 
-    Ok(sm)
+    sm
 }
 
 fn decode_index(rsm: RawSourceMap) -> Result<SourceMapIndex> {
-    let mut sections = vec![];
+    let raw_sections = rsm.sections.unwrap_or_default();
+
+    // the mappings of plain sections of large indexes are decoded up front
+    // on all cores.
+    #[cfg(feature = "rayon")]
+    let mut section_tokens = crate::parallel::decode_section_mappings(&raw_sections).into_iter();
+
+    let mut sections = Vec::with_capacity(raw_sections.len());
+    for mut raw_section in raw_sections {
+        #[cfg(feature = "rayon")]
+        let tokens = section_tokens.next().flatten();
+        #[cfg(not(feature = "rayon"))]
+        let tokens: Option<Result<Vec<RawToken>>> = None;
 
-    for mut raw_section in rsm.sections.unwrap_or_default() {
         sections.push(SourceMapSection::new(
             (raw_section.offset.line, raw_section.offset.column),
             raw_section.url,
-            match raw_section.map.take() {
-                Some(map) => Some(decode_common(*map)?),
-                None => None,
+            match (raw_section.map.take(), tokens) {
+                (Some(map), Some(tokens)) => Some(DecodedMap::Regular(decode_regular_with_tokens(
+                    *map, tokens?,
+                ))),
+                (Some(map), None) => Some(decode_common(*map)?),
+                (None, _) => None,
             },
         ));
     }
@@ -260,15 +246,18 @@ fn decode_index(rsm: RawSourceMap) -> Result<SourceMapIndex> {
         _ => "<invalid>".into(),
     });
 
-    Ok(SourceMapIndex::new_ram_bundle_compatible(
+    let mut smi = SourceMapIndex::new_ram_bundle_compatible(
         file,
         sections,
         rsm.x_facebook_offsets,
         rsm.x_metro_module_paths,
-    ))
+    );
+    smi.set_debug_id(rsm.debug_id);
+
+    Ok(smi)
 }
 
-fn decode_common(rsm: RawSourceMap) -> Result<DecodedMap> {
+pub(crate) fn decode_common(rsm: RawSourceMap) -> Result<DecodedMap> {
     Ok(if rsm.sections.is_some() {
         DecodedMap::Index(decode_index(rsm)?)
     } else if rsm.x_facebook_sources.is_some() {
@@ -299,16 +288,95 @@ pub fn decode_slice(slice: &[u8]) -> Result<DecodedMap> {
     decode_common(rsm)
 }
 
-/// Loads a sourcemap from a data URL
-pub fn decode_data_url(url: &str) -> Result<DecodedMap> {
-    if !url.starts_with(DATA_PREAMBLE) {
+fn is_json_media_type(media_type: &str) -> bool {
+    // RFC 2397 defaults to text/plain if no media type is given
+    media_type.is_empty()
+        || media_type.eq_ignore_ascii_case("application/json")
+        || media_type.eq_ignore_ascii_case("text/json")
+        || media_type.eq_ignore_ascii_case("text/plain")
+        || media_type.eq_ignore_ascii_case("application/octet-stream")
+        || media_type
+            .get(media_type.len().saturating_sub(5)..)
+            .is_some_and(|suffix| suffix.eq_ignore_ascii_case("+json"))
+}
+
+fn percent_decode(data: &str) -> Result<Vec<u8>> {
+    let bytes = data.as_bytes();
+    let mut rv = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = bytes.get(idx + 1..idx + 3).ok_or(Error::InvalidDataUrl)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| Error::InvalidDataUrl)?;
+            rv.push(u8::from_str_radix(hex, 16).map_err(|_| Error::InvalidDataUrl)?);
+            idx += 3;
+        } else {
+            rv.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+    Ok(rv)
+}
+
+/// Extracts the payload of a data URL as defined by RFC 2397.
+///
+/// Sourcemaps are accepted with JSON and plain text media types, with or
+/// without a UTF-8 or ASCII charset and both in base64 and percent encoding.
+pub(crate) fn parse_data_url(url: &str) -> Result<Vec<u8>> {
+    if !url
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+    {
         fail!(Error::InvalidDataUrl);
     }
-    let data_b64 = &url[DATA_PREAMBLE.len()..];
-    let data = data_encoding::BASE64
-        .decode(data_b64.as_bytes())
-        .map_err(|_| Error::InvalidDataUrl)?;
-    decode_slice(&data[..])
+    let (header, data) = url[5..].split_once(',').ok_or(Error::InvalidDataUrl)?;
+
+    let mut params = header.split(';').map(str::trim);
+    let media_type = params.next().unwrap_or("");
+    if !is_json_media_type(media_type) {
+        fail!(Error::InvalidDataUrl);
+    }
+    let mut is_base64 = false;
+    for param in params {
+        if param.eq_ignore_ascii_case("base64") {
+            is_base64 = true;
+        } else if let Some((key, value)) = param.split_once('=') {
+            let value = value.trim_matches('"');
+            if key.trim().eq_ignore_ascii_case("charset")
+                && !value.eq_ignore_ascii_case("utf-8")
+                && !value.eq_ignore_ascii_case("utf8")
+                && !value.eq_ignore_ascii_case("us-ascii")
+            {
+                fail!(Error::InvalidDataUrl);
+            }
+        } else {
+            fail!(Error::InvalidDataUrl);
+        }
+    }
+
+    let data = percent_decode(data)?;
+    if !is_base64 {
+        return Ok(data);
+    }
+    // padding is optional in the wild
+    let mut data: Vec<u8> = data
+        .into_iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    while data.last() == Some(&b'=') {
+        data.pop();
+    }
+    data_encoding::BASE64_NOPAD
+        .decode(&data)
+        .map_err(|_| Error::InvalidDataUrl)
+}
+
+/// Loads a sourcemap from a data URL
+///
+/// The URL is parsed according to RFC 2397 so both base64 and percent
+/// encoded sourcemaps are supported.
+pub fn decode_data_url(url: &str) -> Result<DecodedMap> {
+    decode_slice(&parse_data_url(url)?)
 }
 
 #[test]
@@ -336,3 +404,33 @@ fn test_bad_newline() {
         }
     }
 }
+
+#[test]
+fn test_parse_data_url() {
+    let json = br#"{"version":3}"#;
+    for url in &[
+        "data:application/json;base64,eyJ2ZXJzaW9uIjozfQ==",
+        "data:application/json;base64,eyJ2ZXJzaW9uIjozfQ",
+        "data:application/json;charset=utf-8;base64,eyJ2ZXJzaW9uIjozfQ==",
+        "data:application/json;charset=UTF-8;base64,eyJ2ZXJzaW9uIjozfQ%3D%3D",
+        "DATA:Application/JSON;Charset=\"utf-8\";BASE64,eyJ2ZXJz aW9uIjozfQ==",
+        "data:text/plain;base64,eyJ2ZXJzaW9uIjozfQ==",
+        "data:;base64,eyJ2ZXJzaW9uIjozfQ==",
+        "data:application/json,%7B%22version%22%3A3%7D",
+        "data:application/json;charset=UTF-8,{\"version\":3}",
+        "data:,%7B%22version%22:3%7D",
+    ] {
+        assert_eq!(parse_data_url(url).unwrap(), json, "{url}");
+    }
+
+    for url in &[
+        "application/json;base64,eyJ2ZXJzaW9uIjozfQ==",
+        "data:application/json;base64",
+        "data:image/png;base64,eyJ2ZXJzaW9uIjozfQ==",
+        "data:application/json;charset=latin1,{}",
+        "data:application/json;base64,eyJ2ZXJzaW9uIjozfQ=!",
+        "data:application/json,%7",
+    ] {
+        assert!(parse_data_url(url).is_err(), "{}", url);
+    }
+}
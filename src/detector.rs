@@ -1,4 +1,6 @@
-use std::io::{BufRead, BufReader, Read};
+use std::borrow::Cow;
+use std::io::{BufReader, Read};
+use std::ops::Range;
 use std::str;
 
 use crate::decoder::{decode_data_url, strip_junk_header, StripHeaderReader};
@@ -6,15 +8,18 @@ use crate::errors::Result;
 use crate::jsontypes::MinimalRawSourceMap;
 use crate::types::DecodedMap;
 
+use debugid::DebugId;
 use url::Url;
 
 /// Represents a reference to a sourcemap
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum SourceMapRef {
     /// A regular URL reference
     Ref(String),
     /// A legacy URL reference
     LegacyRef(String),
+    /// A URL reference from a `SourceMap` or `X-SourceMap` HTTP header
+    Header(String),
 }
 
 fn resolve_url(ref_url: &str, minified_url: &Url) -> Option<Url> {
@@ -27,13 +32,61 @@ impl SourceMapRef {
         match *self {
             SourceMapRef::Ref(ref u) => u.as_str(),
             SourceMapRef::LegacyRef(ref u) => u.as_str(),
+            SourceMapRef::Header(ref u) => u.as_str(),
+        }
+    }
+
+    /// Finds a reference in the headers of an HTTP response.
+    ///
+    /// The `SourceMap` header is preferred over the deprecated `X-SourceMap`
+    /// header.  Header names are compared case-insensitively.  The returned
+    /// reference can be resolved against the request URL with `resolve`.
+    pub fn from_headers<I, K, V>(headers: I) -> Option<SourceMapRef>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut legacy = None;
+        for (name, value) in headers {
+            let value = value.as_ref().trim();
+            if value.is_empty() {
+                continue;
+            }
+            let name = name.as_ref();
+            if name.eq_ignore_ascii_case("sourcemap") {
+                return Some(SourceMapRef::Header(value.to_owned()));
+            } else if name.eq_ignore_ascii_case("x-sourcemap") && legacy.is_none() {
+                legacy = Some(value.to_owned());
+            }
+        }
+        legacy.map(SourceMapRef::Header)
+    }
+
+    /// Finds the reference of a file that was fetched over HTTP.
+    ///
+    /// A reference from the response headers takes precedence over a
+    /// reference comment in the body.
+    pub fn from_headers_and_source<I, K, V>(
+        headers: I,
+        source: &[u8],
+    ) -> Result<Option<SourceMapRef>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        match SourceMapRef::from_headers(headers) {
+            Some(reference) => Ok(Some(reference)),
+            None => locate_sourcemap_reference_slice(source),
         }
     }
 
     /// Resolves the reference.
     ///
-    /// The given minified URL needs to be the URL of the minified file.  The
-    /// result is the fully resolved URL of where the source map can be located.
+    /// The given minified URL needs to be the URL of the minified file, for
+    /// header references this is the request URL.  The result is the fully
+    /// resolved URL of where the source map can be located.
     pub fn resolve(&self, minified_url: &str) -> Option<String> {
         let url = self.get_url();
         if url.starts_with("data:") {
@@ -70,19 +123,14 @@ impl SourceMapRef {
 ///
 /// Given a reader to a JavaScript file this tries to find the correct
 /// sourcemap reference comment and return it.
-pub fn locate_sourcemap_reference<R: Read>(rdr: R) -> Result<Option<SourceMapRef>> {
-    for line in BufReader::new(rdr).lines() {
-        let line = line?;
-        if line.starts_with("//# sourceMappingURL=") || line.starts_with("//@ sourceMappingURL=") {
-            let url = str::from_utf8(&line.as_bytes()[21..])?.trim().to_owned();
-            if line.starts_with("//@") {
-                return Ok(Some(SourceMapRef::LegacyRef(url)));
-            } else {
-                return Ok(Some(SourceMapRef::Ref(url)));
-            }
-        }
-    }
-    Ok(None)
+///
+/// Both JavaScript (`//# sourceMappingURL=...`) and CSS
+/// (`/*# sourceMappingURL=... */`) comments are recognized.  If there are
+/// multiple comments the last one wins.
+pub fn locate_sourcemap_reference<R: Read>(mut rdr: R) -> Result<Option<SourceMapRef>> {
+    let mut source = vec![];
+    rdr.read_to_end(&mut source)?;
+    locate_sourcemap_reference_slice(&source)
 }
 
 /// Locates a sourcemap reference in a slice
@@ -90,7 +138,456 @@ pub fn locate_sourcemap_reference<R: Read>(rdr: R) -> Result<Option<SourceMapRef
 /// This is an alternative to `locate_sourcemap_reference` that operates
 /// on slices.
 pub fn locate_sourcemap_reference_slice(slice: &[u8]) -> Result<Option<SourceMapRef>> {
-    locate_sourcemap_reference(slice)
+    Ok(locate_sourcemap_comment(slice).map(SourceMapComment::into_ref))
+}
+
+/// The kind of file a sourcemap reference comment is in.
+///
+/// This decides the comment syntax: JavaScript uses line comments
+/// (`//# sourceMappingURL=...`) and CSS uses block comments
+/// (`/*# sourceMappingURL=... */`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceKind {
+    /// A JavaScript file
+    JavaScript,
+    /// A CSS file
+    Css,
+}
+
+impl SourceKind {
+    fn format_comment(self, url: &str) -> String {
+        self.format_directive("sourceMappingURL", url)
+    }
+
+    pub(crate) fn format_directive(self, name: &str, value: &str) -> String {
+        match self {
+            SourceKind::JavaScript => format!("//# {name}={value}"),
+            SourceKind::Css => format!("/*# {name}={value} */"),
+        }
+    }
+}
+
+/// A sourcemap reference comment in a minified file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapComment {
+    range: Range<usize>,
+    url_range: Range<usize>,
+    kind: SourceKind,
+    reference: SourceMapRef,
+}
+
+impl SourceMapComment {
+    /// Returns the byte range of the whole comment.
+    ///
+    /// This does not include the line terminator.
+    pub fn get_range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the byte range of the URL in the comment.
+    pub fn get_url_range(&self) -> Range<usize> {
+        self.url_range.clone()
+    }
+
+    /// Returns the syntax of the comment.
+    pub fn get_kind(&self) -> SourceKind {
+        self.kind
+    }
+
+    /// Returns the reference.
+    pub fn get_ref(&self) -> &SourceMapRef {
+        &self.reference
+    }
+
+    /// Converts the comment into the reference.
+    pub fn into_ref(self) -> SourceMapRef {
+        self.reference
+    }
+}
+
+/// The directives that are recognized in comments of minified files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    SourceMappingUrl,
+    SourceUrl,
+    DebugId,
+}
+
+impl Directive {
+    fn prefix(self) -> &'static [u8] {
+        match self {
+            Directive::SourceMappingUrl => b" sourceMappingURL=",
+            Directive::SourceUrl => b" sourceURL=",
+            Directive::DebugId => b" debugId=",
+        }
+    }
+}
+
+/// A directive comment as found in a line.
+struct ParsedComment {
+    directive: Directive,
+    range: Range<usize>,
+    url_range: Range<usize>,
+    kind: SourceKind,
+    legacy: bool,
+    url: String,
+}
+
+impl ParsedComment {
+    fn into_sourcemap_comment(self) -> SourceMapComment {
+        SourceMapComment {
+            range: self.range,
+            url_range: self.url_range,
+            kind: self.kind,
+            reference: if self.legacy {
+                SourceMapRef::LegacyRef(self.url)
+            } else {
+                SourceMapRef::Ref(self.url)
+            },
+        }
+    }
+}
+
+/// Parses a directive comment that starts at `start` in a line.
+fn parse_comment_at(offset: usize, line: &[u8], start: usize) -> Option<ParsedComment> {
+    let rest = &line[start..];
+    let (kind, legacy) = match rest.get(..3)? {
+        b"//#" => (SourceKind::JavaScript, false),
+        b"//@" => (SourceKind::JavaScript, true),
+        b"/*#" => (SourceKind::Css, false),
+        b"/*@" => (SourceKind::Css, true),
+        _ => return None,
+    };
+    let directive = [
+        Directive::SourceMappingUrl,
+        Directive::SourceUrl,
+        Directive::DebugId,
+    ]
+    .iter()
+    .copied()
+    .find(|directive| rest[3..].starts_with(directive.prefix()))?;
+
+    let url_start = start + 3 + directive.prefix().len();
+    let (url_end, end) = match kind {
+        SourceKind::JavaScript => (line.len(), line.len()),
+        SourceKind::Css => match line[url_start..].windows(2).position(|w| w == b"*/") {
+            Some(pos) => (url_start + pos, url_start + pos + 2),
+            None => (line.len(), line.len()),
+        },
+    };
+    let raw_url = str::from_utf8(&line[url_start..url_end]).ok()?;
+    let url = raw_url.trim();
+    let url_start = url_start + (raw_url.len() - raw_url.trim_start().len());
+    let end = match kind {
+        SourceKind::JavaScript => url_start + url.len(),
+        SourceKind::Css => end,
+    };
+
+    Some(ParsedComment {
+        directive,
+        range: offset + start..offset + end,
+        url_range: offset + url_start..offset + url_start + url.len(),
+        kind,
+        legacy,
+        url: url.to_owned(),
+    })
+}
+
+/// Finds all directive comments in a line, from left to right.
+///
+/// Line comments may follow code and block comments can be anywhere.
+/// Quoted strings are skipped so that directives inside string literals
+/// are not picked up.  Once a regular line comment starts, only block
+/// comment directives are looked for as CSS has no line comments and
+/// `//` commonly shows up in its URLs.
+fn parse_comment_line(offset: usize, line: &[u8]) -> Vec<ParsedComment> {
+    let mut rv = vec![];
+    let mut quote = None;
+    let mut in_line_comment = false;
+    let mut idx = 0;
+
+    while idx < line.len() {
+        let byte = line[idx];
+        if let Some(q) = quote {
+            if byte == b'\\' {
+                idx += 1;
+            } else if byte == q {
+                quote = None;
+            }
+            idx += 1;
+            continue;
+        }
+
+        match (byte, line.get(idx + 1)) {
+            (b'"', _) | (b'\'', _) | (b'`', _) if !in_line_comment => {
+                quote = Some(byte);
+            }
+            (b'/', Some(b'/')) if !in_line_comment => match parse_comment_at(offset, line, idx) {
+                Some(comment) => {
+                    rv.push(comment);
+                    break;
+                }
+                None => in_line_comment = true,
+            },
+            (b'/', Some(b'*')) => {
+                if let Some(comment) = parse_comment_at(offset, line, idx) {
+                    rv.push(comment);
+                }
+                match line[idx + 2..].windows(2).position(|w| w == b"*/") {
+                    Some(pos) => idx += pos + 3,
+                    None => break,
+                }
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+
+    rv
+}
+
+/// Iterates over all directive comments from the end of a file.
+struct CommentIter<'a> {
+    source: &'a [u8],
+    end: Option<usize>,
+    pending: Vec<ParsedComment>,
+}
+
+impl<'a> CommentIter<'a> {
+    fn new(source: &'a [u8]) -> CommentIter<'a> {
+        CommentIter {
+            source,
+            end: Some(source.len()),
+            pending: vec![],
+        }
+    }
+}
+
+impl<'a> Iterator for CommentIter<'a> {
+    type Item = ParsedComment;
+
+    fn next(&mut self) -> Option<ParsedComment> {
+        loop {
+            if let Some(comment) = self.pending.pop() {
+                return Some(comment);
+            }
+
+            let line_end = self.end?;
+            let start = self.source[..line_end]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |pos| pos + 1);
+            self.end = start.checked_sub(1);
+            let mut line = &self.source[start..line_end];
+            if line.last() == Some(&b'\r') {
+                line = &line[..line.len() - 1];
+            }
+            self.pending = parse_comment_line(start, line);
+        }
+    }
+}
+
+/// Iterates over all sourcemap reference comments from the end of a file.
+///
+/// The first item is the comment that is used as the reference.  This is
+/// mostly useful for debugging files with multiple references, for
+/// instance bundles that embed other bundles.
+pub struct SourceMapCommentIter<'a> {
+    comments: CommentIter<'a>,
+}
+
+impl<'a> Iterator for SourceMapCommentIter<'a> {
+    type Item = SourceMapComment;
+
+    fn next(&mut self) -> Option<SourceMapComment> {
+        self.comments
+            .find(|comment| comment.directive == Directive::SourceMappingUrl)
+            .map(ParsedComment::into_sourcemap_comment)
+    }
+}
+
+/// Returns an iterator over all sourcemap reference comments in a file,
+/// starting with the last one.
+pub fn sourcemap_comment_candidates(source: &[u8]) -> SourceMapCommentIter<'_> {
+    SourceMapCommentIter {
+        comments: CommentIter::new(source),
+    }
+}
+
+/// Locates the sourcemap reference comment in a minified file.
+///
+/// This understands JavaScript (`//# sourceMappingURL=`), legacy
+/// (`//@ sourceMappingURL=`) and CSS (`/*# sourceMappingURL= */`) comments.
+/// The file is scanned from the end and the last comment wins, as required
+/// by the specification.
+pub fn locate_sourcemap_comment(source: &[u8]) -> Option<SourceMapComment> {
+    sourcemap_comment_candidates(source).next()
+}
+
+/// Locates the `sourceURL` of a minified file.
+///
+/// Code that is evaluated with `eval` or `new Function` is usually named
+/// with a `//# sourceURL=` comment.  The legacy `//@` and the CSS
+/// (`/*# sourceURL= */`) forms are understood as well and the last comment
+/// wins.
+pub fn locate_source_url(source: &[u8]) -> Option<String> {
+    CommentIter::new(source)
+        .find(|comment| comment.directive == Directive::SourceUrl)
+        .map(|comment| comment.url)
+}
+
+/// Locates the debug ID of a minified file.
+///
+/// The debug ID is given in a `//# debugId=` comment and ties the file to
+/// its sourcemap which carries the same ID in its `debugId` field.
+pub fn locate_debug_id(source: &[u8]) -> Option<DebugId> {
+    CommentIter::new(source)
+        .find(|comment| comment.directive == Directive::DebugId)
+        .and_then(|comment| comment.url.parse().ok())
+}
+
+/// The references found in the comments of a minified file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceReferences {
+    source_url: Option<String>,
+    sourcemap_ref: Option<SourceMapRef>,
+}
+
+impl SourceReferences {
+    /// Returns the `sourceURL` of the file if there is one.
+    pub fn get_source_url(&self) -> Option<&str> {
+        self.source_url.as_deref()
+    }
+
+    /// Returns the sourcemap reference of the file if there is one.
+    pub fn get_sourcemap_ref(&self) -> Option<&SourceMapRef> {
+        self.sourcemap_ref.as_ref()
+    }
+}
+
+/// Locates both the `sourceURL` and the sourcemap reference of a minified
+/// file in a single pass.
+pub fn locate_source_references(source: &[u8]) -> SourceReferences {
+    let mut rv = SourceReferences::default();
+    for comment in CommentIter::new(source) {
+        match comment.directive {
+            Directive::SourceMappingUrl if rv.sourcemap_ref.is_none() => {
+                rv.sourcemap_ref = Some(comment.into_sourcemap_comment().into_ref());
+            }
+            Directive::SourceUrl if rv.source_url.is_none() => {
+                rv.source_url = Some(comment.url);
+            }
+            _ => {}
+        }
+        if rv.sourcemap_ref.is_some() && rv.source_url.is_some() {
+            break;
+        }
+    }
+    rv
+}
+
+/// Returns the range to remove together with a comment.
+///
+/// If the comment is on a line of its own the line terminator goes with
+/// it, otherwise whitespace before the comment is removed.
+fn removal_range(source: &str, comment: &SourceMapComment) -> Range<usize> {
+    let is_newline = |c: char| c == '\n' || c == '\r';
+    let range = comment.get_range();
+    let line_start = source[..range.start]
+        .rfind(is_newline)
+        .map_or(0, |pos| pos + 1);
+    let before = &source[line_start..range.start];
+    let after = &source[range.end..];
+    let line_rest = after.find(is_newline).map_or(after, |pos| &after[..pos]);
+
+    if !before.trim().is_empty() {
+        let start = line_start + before.trim_end().len();
+        return start..range.end;
+    }
+    if !line_rest.trim().is_empty() {
+        return range;
+    }
+    match after.find(is_newline) {
+        Some(pos) if after[pos..].starts_with("\r\n") => line_start..range.end + pos + 2,
+        Some(pos) => line_start..range.end + pos + 1,
+        // the last line: remove the line terminator before it instead
+        None => {
+            let head = &source[..line_start];
+            let terminator = if head.ends_with("\r\n") {
+                2
+            } else if head.ends_with(is_newline) {
+                1
+            } else {
+                0
+            };
+            line_start - terminator..source.len()
+        }
+    }
+}
+
+/// Replaces the URL of the sourcemap reference comment.
+///
+/// The comment keeps its syntax but legacy `//@` comments are upgraded to
+/// `//#`.  Returns `None` if the source has no reference comment.
+pub fn replace_sourcemap_reference(source: &str, url: &str) -> Option<String> {
+    let comment = locate_sourcemap_comment(source.as_bytes())?;
+    let range = comment.get_range();
+    let mut rv = String::with_capacity(source.len() + url.len());
+    rv.push_str(&source[..range.start]);
+    rv.push_str(&comment.get_kind().format_comment(url));
+    rv.push_str(&source[range.end..]);
+    Some(rv)
+}
+
+/// Removes the sourcemap reference comment.
+///
+/// If the comment is on a line of its own the whole line is removed.
+pub fn strip_sourcemap_reference(source: &str) -> Cow<'_, str> {
+    match locate_sourcemap_comment(source.as_bytes()) {
+        Some(comment) => {
+            let range = removal_range(source, &comment);
+            let mut rv = String::with_capacity(source.len());
+            rv.push_str(&source[..range.start]);
+            rv.push_str(&source[range.end..]);
+            Cow::Owned(rv)
+        }
+        None => Cow::Borrowed(source),
+    }
+}
+
+/// Appends a sourcemap reference comment to a source.
+///
+/// An existing reference comment is removed first.  The new comment goes
+/// on a line of its own at the end of the source and a trailing newline is
+/// kept if the source had one.
+pub fn append_sourcemap_reference(source: &str, url: &str, kind: SourceKind) -> String {
+    let stripped = strip_sourcemap_reference(source);
+    append_line(&stripped, source, &kind.format_comment(url))
+}
+
+/// Appends a line to `body` which is `source` with things removed.
+///
+/// The line terminator style and the trailing newline of `source` are
+/// kept.
+pub(crate) fn append_line(body: &str, source: &str, line: &str) -> String {
+    let had_newline = source.ends_with('\n');
+    let body = body.trim_end_matches(&['\n', '\r'][..]);
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut rv = String::with_capacity(body.len() + line.len() + 2);
+    rv.push_str(body);
+    if !body.is_empty() {
+        rv.push_str(newline);
+    }
+    rv.push_str(line);
+    if had_newline {
+        rv.push_str(newline);
+    }
+    rv
 }
 
 fn is_sourcemap_common(rsm: MinimalRawSourceMap) -> bool {
@@ -0,0 +1,301 @@
+//! Pairs minified files with their sourcemaps in a build output directory.
+//!
+//! ```rust,no_run
+//! use sourcemap::discovery::discover;
+//! let discovery = discover("dist").unwrap();
+//! for pair in discovery.pairs() {
+//!     println!("{} -> {:?}", pair.get_minified_path().display(), pair.get_sourcemap());
+//! }
+//! for path in discovery.orphan_maps() {
+//!     println!("orphan map: {}", path.display());
+//! }
+//! ```
+//!
+//! Every `.js`, `.mjs`, `.cjs` and `.css` file is matched with a sourcemap,
+//! trying these in order:
+//!
+//! 1. the `sourceMappingURL` comment, resolved relative to the file
+//! 2. an embedded data URL in the `sourceMappingURL` comment
+//! 3. a sibling file with `.map` appended to the file name
+//! 4. a `.map` file with the same debug ID as the `//# debugId=` comment
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use debugid::DebugId;
+use serde::Deserialize;
+
+use crate::decoder::strip_junk_header;
+use crate::detector::{
+    locate_debug_id, locate_sourcemap_reference_slice, SourceKind, SourceMapRef,
+};
+use crate::errors::Result;
+use crate::jsontypes::deserialize_debug_id;
+
+/// How a minified file was paired with its sourcemap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchKind {
+    /// The `sourceMappingURL` comment points to the sourcemap file.
+    Reference,
+    /// The `sourceMappingURL` comment holds the sourcemap as data URL.
+    DataUrl,
+    /// The sourcemap is named like the file with `.map` appended.
+    Sibling,
+    /// The sourcemap has the same debug ID as the file.
+    DebugId,
+}
+
+/// Where the sourcemap of a minified file is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveredMap {
+    /// The sourcemap is in a file.
+    Path(PathBuf),
+    /// The sourcemap is embedded as data URL.
+    Embedded(SourceMapRef),
+}
+
+/// A minified file together with its sourcemap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePair {
+    minified_path: PathBuf,
+    kind: SourceKind,
+    sourcemap: DiscoveredMap,
+    match_kind: MatchKind,
+    debug_id: Option<DebugId>,
+}
+
+impl FilePair {
+    /// Returns the path of the minified file.
+    pub fn get_minified_path(&self) -> &Path {
+        &self.minified_path
+    }
+
+    /// Returns the kind of the minified file.
+    pub fn get_kind(&self) -> SourceKind {
+        self.kind
+    }
+
+    /// Returns the sourcemap of the file.
+    pub fn get_sourcemap(&self) -> &DiscoveredMap {
+        &self.sourcemap
+    }
+
+    /// Returns the path of the sourcemap unless it is embedded.
+    pub fn get_sourcemap_path(&self) -> Option<&Path> {
+        match self.sourcemap {
+            DiscoveredMap::Path(ref path) => Some(path),
+            DiscoveredMap::Embedded(_) => None,
+        }
+    }
+
+    /// Returns how the sourcemap was found.
+    pub fn get_match_kind(&self) -> MatchKind {
+        self.match_kind
+    }
+
+    /// Returns the debug ID of the minified file in case there is one.
+    pub fn get_debug_id(&self) -> Option<DebugId> {
+        self.debug_id
+    }
+}
+
+/// A minified file whose reference points to a file that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingReference {
+    minified_path: PathBuf,
+    reference: SourceMapRef,
+    resolved_path: Option<PathBuf>,
+}
+
+impl MissingReference {
+    /// Returns the path of the minified file.
+    pub fn get_minified_path(&self) -> &Path {
+        &self.minified_path
+    }
+
+    /// Returns the reference as found in the file.
+    pub fn get_ref(&self) -> &SourceMapRef {
+        &self.reference
+    }
+
+    /// Returns the path the reference resolves to if it is a local path.
+    pub fn get_resolved_path(&self) -> Option<&Path> {
+        self.resolved_path.as_deref()
+    }
+}
+
+/// The result of pairing the files in a directory.
+///
+/// All lists are sorted by path.
+#[derive(Debug, Clone, Default)]
+pub struct Discovery {
+    pairs: Vec<FilePair>,
+    unpaired: Vec<PathBuf>,
+    missing_references: Vec<MissingReference>,
+    orphan_maps: Vec<PathBuf>,
+}
+
+impl Discovery {
+    /// Returns the minified files that were paired with a sourcemap.
+    pub fn pairs(&self) -> &[FilePair] {
+        &self.pairs
+    }
+
+    /// Returns the minified files for which no sourcemap was found.
+    pub fn unpaired_files(&self) -> &[PathBuf] {
+        &self.unpaired
+    }
+
+    /// Returns the references that point to missing files.
+    ///
+    /// Such files might still have been paired by one of the fallbacks.
+    pub fn missing_references(&self) -> &[MissingReference] {
+        &self.missing_references
+    }
+
+    /// Returns the `.map` files that no minified file was paired with.
+    pub fn orphan_maps(&self) -> &[PathBuf] {
+        &self.orphan_maps
+    }
+}
+
+#[derive(Deserialize)]
+struct DebugIdRawSourceMap {
+    #[serde(
+        rename = "debugId",
+        alias = "debug_id",
+        default,
+        deserialize_with = "deserialize_debug_id"
+    )]
+    debug_id: Option<DebugId>,
+}
+
+fn get_source_kind(path: &Path) -> Option<SourceKind> {
+    match path.extension()?.to_str()? {
+        "js" | "mjs" | "cjs" => Some(SourceKind::JavaScript),
+        "css" => Some(SourceKind::Css),
+        _ => None,
+    }
+}
+
+fn is_map_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("map")
+}
+
+fn walk_dir(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            walk_dir(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn map_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Walks a directory and pairs all minified files with their sourcemaps.
+///
+/// See the [module documentation](index.html) for how files are matched.
+/// Paths in the result are absolute.
+pub fn discover<P: AsRef<Path>>(root: P) -> Result<Discovery> {
+    let root = fs::canonicalize(root)?;
+    let mut files = vec![];
+    walk_dir(&root, &mut files)?;
+    files.sort();
+
+    let maps: BTreeSet<PathBuf> = files.iter().filter(|p| is_map_file(p)).cloned().collect();
+    let mut maps_by_debug_id: Option<HashMap<DebugId, PathBuf>> = None;
+    let mut used_maps = BTreeSet::new();
+    let mut rv = Discovery::default();
+
+    for path in files {
+        let kind = match get_source_kind(&path) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let source = fs::read(&path)?;
+        let debug_id = locate_debug_id(&source);
+        let mut found = None;
+
+        if let Some(reference) = locate_sourcemap_reference_slice(&source)? {
+            if reference.get_url().starts_with("data:") {
+                found = Some((DiscoveredMap::Embedded(reference), MatchKind::DataUrl));
+            } else {
+                match reference.resolve_path(&path) {
+                    Some(resolved) if resolved.is_file() => {
+                        found = Some((
+                            DiscoveredMap::Path(map_path(&resolved)),
+                            MatchKind::Reference,
+                        ));
+                    }
+                    resolved_path => rv.missing_references.push(MissingReference {
+                        minified_path: path.clone(),
+                        reference,
+                        resolved_path,
+                    }),
+                }
+            }
+        }
+
+        if found.is_none() {
+            let mut sibling = path.clone().into_os_string();
+            sibling.push(".map");
+            let sibling = PathBuf::from(sibling);
+            if maps.contains(&sibling) {
+                found = Some((DiscoveredMap::Path(sibling), MatchKind::Sibling));
+            }
+        }
+
+        if let (None, Some(debug_id)) = (&found, debug_id) {
+            let by_debug_id = maps_by_debug_id.get_or_insert_with(|| index_debug_ids(&maps));
+            if let Some(map) = by_debug_id.get(&debug_id) {
+                found = Some((DiscoveredMap::Path(map.clone()), MatchKind::DebugId));
+            }
+        }
+
+        match found {
+            Some((sourcemap, match_kind)) => {
+                if let DiscoveredMap::Path(ref map) = sourcemap {
+                    used_maps.insert(map.clone());
+                }
+                rv.pairs.push(FilePair {
+                    minified_path: path,
+                    kind,
+                    sourcemap,
+                    match_kind,
+                    debug_id,
+                });
+            }
+            None => rv.unpaired.push(path),
+        }
+    }
+
+    rv.orphan_maps = maps.difference(&used_maps).cloned().collect();
+    Ok(rv)
+}
+
+/// Reads the debug IDs of all sourcemaps.
+///
+/// Files that cannot be read or are not sourcemaps are skipped.
+fn index_debug_ids(maps: &BTreeSet<PathBuf>) -> HashMap<DebugId, PathBuf> {
+    let mut rv = HashMap::new();
+    for path in maps {
+        let debug_id = fs::read(path)
+            .ok()
+            .and_then(|contents| {
+                let contents = strip_junk_header(&contents).ok()?;
+                serde_json::from_slice::<DebugIdRawSourceMap>(contents).ok()
+            })
+            .and_then(|rsm| rsm.debug_id);
+        if let Some(debug_id) = debug_id {
+            rv.entry(debug_id).or_insert_with(|| path.clone());
+        }
+    }
+    rv
+}
@@ -1,11 +1,212 @@
 use std::io::Write;
 
+use debugid::DebugId;
+use serde::Serialize;
 use serde_json::Value;
 
-use crate::errors::Result;
-use crate::jsontypes::{RawSection, RawSectionOffset, RawSourceMap};
+use crate::decoder::DATA_PREAMBLE;
+use crate::errors::{Error, Result};
+use crate::jsontypes::{FacebookSources, RawSection, RawSectionOffset, RawSourceMap};
 use crate::types::{DecodedMap, SourceMap, SourceMapIndex};
-use crate::vlq::encode_vlq;
+use crate::vlq::{MappingsDecoder, MappingsEncoder};
+
+/// How sources without contents are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MissingSourceContents {
+    /// `sourcesContent` is only written if at least one source has contents.
+    Omit,
+    /// `sourcesContent` is always written with `null` for missing contents.
+    Null,
+}
+
+/// Controls how sourcemaps are written with `to_writer_with`.
+///
+/// The output only depends on the sourcemap and these options so that
+/// writing the same sourcemap always produces the same bytes.
+///
+/// Default configuration:
+///
+/// * `pretty`: false
+/// * `with_names`: true
+/// * `with_source_contents`: true
+/// * `missing_source_contents`: `MissingSourceContents::Omit`
+/// * `source_root`: None
+/// * `spec_key_order`: false
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncodeOptions<'a> {
+    /// If enabled the JSON is indented.
+    pub pretty: bool,
+    /// If disabled `names` is omitted and tokens lose their names.
+    pub with_names: bool,
+    /// If disabled `sourcesContent` is omitted.
+    pub with_source_contents: bool,
+    /// How sources without contents are written.
+    pub missing_source_contents: MissingSourceContents,
+    /// If set this is written as `sourceRoot` and the prefix is stripped
+    /// from all sources.  Writing fails with `Error::SourceOutsideRoot` if
+    /// a source does not start with the root.
+    pub source_root: Option<&'a str>,
+    /// If enabled keys are written in the order of the specification
+    /// instead of the order this library historically used.
+    pub spec_key_order: bool,
+}
+
+impl<'a> Default for EncodeOptions<'a> {
+    fn default() -> EncodeOptions<'a> {
+        EncodeOptions {
+            pretty: false,
+            with_names: true,
+            with_source_contents: true,
+            missing_source_contents: MissingSourceContents::Omit,
+            source_root: None,
+            spec_key_order: false,
+        }
+    }
+}
+
+/// A `RawSourceMap` with its keys in the order of the specification.
+#[derive(Serialize)]
+struct SpecRawSourceMap {
+    version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<Value>,
+    #[serde(rename = "sourceRoot", skip_serializing_if = "Option::is_none")]
+    source_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources: Option<Vec<Option<String>>>,
+    #[serde(rename = "sourcesContent", skip_serializing_if = "Option::is_none")]
+    sources_content: Option<Vec<Option<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    names: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mappings: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sections: Option<Vec<SpecRawSection>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x_facebook_offsets: Option<Vec<Option<u32>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x_metro_module_paths: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x_facebook_sources: FacebookSources,
+    #[serde(rename = "debugId", skip_serializing_if = "Option::is_none")]
+    debug_id: Option<DebugId>,
+}
+
+#[derive(Serialize)]
+struct SpecRawSection {
+    offset: RawSectionOffset,
+    url: Option<String>,
+    map: Option<Box<SpecRawSourceMap>>,
+}
+
+impl From<RawSourceMap> for SpecRawSourceMap {
+    fn from(rsm: RawSourceMap) -> SpecRawSourceMap {
+        SpecRawSourceMap {
+            version: rsm.version,
+            file: rsm.file,
+            source_root: rsm.source_root,
+            sources: rsm.sources,
+            sources_content: rsm.sources_content,
+            names: rsm.names,
+            mappings: rsm.mappings,
+            sections: rsm.sections.map(|sections| {
+                sections
+                    .into_iter()
+                    .map(|section| SpecRawSection {
+                        offset: section.offset,
+                        url: section.url,
+                        map: section.map.map(|map| Box::new((*map).into())),
+                    })
+                    .collect()
+            }),
+            x_facebook_offsets: rsm.x_facebook_offsets,
+            x_metro_module_paths: rsm.x_metro_module_paths,
+            x_facebook_sources: rsm.x_facebook_sources,
+            debug_id: rsm.debug_id,
+        }
+    }
+}
+
+/// Re-encodes mappings without name references.
+fn strip_mapping_names(mappings: &str) -> String {
+    let mut rv = String::new();
+    let mut encoder = MappingsEncoder::new();
+    let mut prev_token = None;
+
+    // the mappings were produced by this library so they are valid
+    for mut raw in MappingsDecoder::new(mappings).flatten() {
+        raw.name_id = !0;
+        if Some(raw) != prev_token {
+            encoder.encode(&mut rv, &raw);
+            prev_token = Some(raw);
+        }
+    }
+
+    rv
+}
+
+fn apply_options(rsm: &mut RawSourceMap, options: &EncodeOptions<'_>) -> Result<()> {
+    if let Some(ref mut sections) = rsm.sections {
+        for section in sections {
+            if let Some(ref mut map) = section.map {
+                apply_options(map, options)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if !options.with_names {
+        rsm.names = None;
+        if let Some(ref mut mappings) = rsm.mappings {
+            *mappings = strip_mapping_names(mappings);
+        }
+    }
+
+    if !options.with_source_contents {
+        rsm.sources_content = None;
+    } else if options.missing_source_contents == MissingSourceContents::Null {
+        let source_count = rsm.sources.as_ref().map_or(0, Vec::len);
+        rsm.sources_content
+            .get_or_insert_with(Vec::new)
+            .resize(source_count, None);
+    }
+
+    if let Some(root) = options.source_root {
+        let prefix = if root.is_empty() || root.ends_with('/') {
+            root.to_string()
+        } else {
+            format!("{root}/")
+        };
+        // decoders prepend the root to every source, so it can only be
+        // used if all sources are below it
+        let mut sources = rsm.sources.iter().flatten().flatten();
+        if let Some(source) = sources.find(|source| !source.starts_with(&prefix)) {
+            return Err(Error::SourceOutsideRoot(source.clone()));
+        }
+        for source in rsm.sources.iter_mut().flatten().flatten() {
+            source.drain(..prefix.len());
+        }
+        rsm.source_root = Some(root.to_string());
+    }
+
+    Ok(())
+}
+
+pub fn encode_with<M: Encodable, W: Write>(
+    sm: &M,
+    mut w: W,
+    options: &EncodeOptions<'_>,
+) -> Result<()> {
+    let mut rsm = sm.as_raw_sourcemap();
+    apply_options(&mut rsm, options)?;
+    match (options.spec_key_order, options.pretty) {
+        (false, false) => serde_json::to_writer(&mut w, &rsm)?,
+        (false, true) => serde_json::to_writer_pretty(&mut w, &rsm)?,
+        (true, false) => serde_json::to_writer(&mut w, &SpecRawSourceMap::from(rsm))?,
+        (true, true) => serde_json::to_writer_pretty(&mut w, &SpecRawSourceMap::from(rsm))?,
+    }
+    Ok(())
+}
 
 pub trait Encodable {
     fn as_raw_sourcemap(&self) -> RawSourceMap;
@@ -17,51 +218,32 @@ pub fn encode<M: Encodable, W: Write>(sm: &M, mut w: W) -> Result<()> {
     Ok(())
 }
 
-fn encode_vlq_diff(out: &mut String, a: u32, b: u32) {
-    encode_vlq(out, i64::from(a) - i64::from(b))
+pub fn encode_data_url<M: Encodable>(sm: &M) -> Result<String> {
+    let mut buf = vec![];
+    encode(sm, &mut buf)?;
+    Ok(format!(
+        "{}{}",
+        DATA_PREAMBLE,
+        data_encoding::BASE64.encode(&buf)
+    ))
 }
 
 fn serialize_mappings(sm: &SourceMap) -> String {
     let mut rv = String::new();
-    // dst == minified == generated
-    let mut prev_dst_line = 0;
-    let mut prev_dst_col = 0;
-    let mut prev_src_line = 0;
-    let mut prev_src_col = 0;
-    let mut prev_name_id = 0;
-    let mut prev_src_id = 0;
-
-    for (idx, token) in sm.tokens().enumerate() {
-        let idx = idx as u32;
-
-        if token.get_dst_line() != prev_dst_line {
-            prev_dst_col = 0;
-            while token.get_dst_line() != prev_dst_line {
-                rv.push(';');
-                prev_dst_line += 1;
-            }
-        } else if idx > 0 {
-            if Some(&token) == sm.get_token(idx - 1).as_ref() {
-                continue;
-            }
-            rv.push(',');
+    let mut encoder = MappingsEncoder::new();
+    let mut prev_token = None;
+
+    for token in sm.tokens() {
+        if Some(&token) == prev_token.as_ref() {
+            continue;
         }
+        prev_token = Some(token);
 
-        encode_vlq_diff(&mut rv, token.get_dst_col(), prev_dst_col);
-        prev_dst_col = token.get_dst_col();
-
-        if token.has_source() {
-            encode_vlq_diff(&mut rv, token.get_src_id(), prev_src_id);
-            prev_src_id = token.get_src_id();
-            encode_vlq_diff(&mut rv, token.get_src_line(), prev_src_line);
-            prev_src_line = token.get_src_line();
-            encode_vlq_diff(&mut rv, token.get_src_col(), prev_src_col);
-            prev_src_col = token.get_src_col();
-            if token.has_name() {
-                encode_vlq_diff(&mut rv, token.get_name_id(), prev_name_id);
-                prev_name_id = token.get_name_id();
-            }
+        let mut raw = token.get_raw_token();
+        if !token.has_name() {
+            raw.name_id = !0;
         }
+        encoder.encode(&mut rv, &raw);
     }
 
     rv
@@ -93,6 +275,7 @@ impl Encodable for SourceMap {
             x_facebook_offsets: None,
             x_metro_module_paths: None,
             x_facebook_sources: None,
+            debug_id: self.get_debug_id(),
         }
     }
 }
@@ -124,6 +307,7 @@ impl Encodable for SourceMapIndex {
             x_facebook_offsets: None,
             x_metro_module_paths: None,
             x_facebook_sources: None,
+            debug_id: self.get_debug_id(),
         }
     }
 }
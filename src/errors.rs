@@ -15,6 +15,9 @@ pub enum Error {
     #[cfg(feature = "ram_bundle")]
     /// a scroll error
     Scroll(scroll::Error),
+    #[cfg(feature = "artifact_bundle")]
+    /// a zip error
+    Zip(zip::result::ZipError),
     /// a std::str::Utf8Error
     Utf8(str::Utf8Error),
     /// a JSON parsing related failure
@@ -45,6 +48,16 @@ pub enum Error {
     InvalidRamBundleEntry,
     /// Tried to operate on a non RAM bundle file
     NotARamBundle,
+    /// The magic of a sourcemap cache did not match
+    InvalidCacheMagic,
+    /// The sourcemap cache was written in an unsupported format version
+    UnsupportedCacheVersion(u32),
+    /// The sourcemap cache was truncated or otherwise malformed
+    InvalidCacheData,
+    /// A different file was already added to an artifact bundle by this URL
+    ConflictingArtifact(String),
+    /// A source is not below the source root it should be written with
+    SourceOutsideRoot(String),
 }
 
 impl From<io::Error> for Error {
@@ -60,6 +73,13 @@ impl From<scroll::Error> for Error {
     }
 }
 
+#[cfg(feature = "artifact_bundle")]
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        Error::Zip(err)
+    }
+}
+
 impl From<string::FromUtf8Error> for Error {
     fn from(err: string::FromUtf8Error) -> Error {
         From::from(err.utf8_error())
@@ -84,6 +104,8 @@ impl error::Error for Error {
             Error::Io(ref err) => Some(err),
             #[cfg(feature = "ram_bundle")]
             Error::Scroll(ref err) => Some(err),
+            #[cfg(feature = "artifact_bundle")]
+            Error::Zip(ref err) => Some(err),
             Error::Utf8(ref err) => Some(err),
             Error::BadJson(ref err) => Some(err),
             _ => None,
@@ -99,6 +121,8 @@ impl fmt::Display for Error {
             Error::BadJson(ref err) => write!(f, "bad json: {err}"),
             #[cfg(feature = "ram_bundle")]
             Error::Scroll(ref err) => write!(f, "parse error: {err}"),
+            #[cfg(feature = "artifact_bundle")]
+            Error::Zip(ref err) => write!(f, "zip error: {err}"),
             Error::VlqLeftover => write!(f, "leftover cur/shift in vlq decode"),
             Error::VlqNoValues => write!(f, "vlq decode did not produce any values"),
             Error::VlqOverflow => write!(f, "vlq decode caused an overflow"),
@@ -114,6 +138,17 @@ impl fmt::Display for Error {
             Error::InvalidRamBundleIndex => write!(f, "invalid module index in ram bundle"),
             Error::InvalidRamBundleEntry => write!(f, "invalid ram bundle module entry"),
             Error::NotARamBundle => write!(f, "not a ram bundle"),
+            Error::InvalidCacheMagic => write!(f, "invalid magic number for sourcemap cache"),
+            Error::UnsupportedCacheVersion(version) => {
+                write!(f, "unsupported sourcemap cache version {version}")
+            }
+            Error::InvalidCacheData => write!(f, "malformed sourcemap cache"),
+            Error::ConflictingArtifact(ref url) => {
+                write!(f, "a different artifact was already added as {url}")
+            }
+            Error::SourceOutsideRoot(ref source) => {
+                write!(f, "source {source} is not below the source root")
+            }
         }
     }
 }
@@ -1,8 +1,9 @@
 use crate::decoder::{decode, decode_regular, decode_slice};
-use crate::encoder::{encode, Encodable};
+use crate::encoder::{encode, encode_data_url, encode_with, Encodable, EncodeOptions};
 use crate::errors::{Error, Result};
 use crate::jsontypes::{FacebookScopeMapping, FacebookSources, RawSourceMap};
-use crate::types::{DecodedMap, RewriteOptions, SourceMap};
+use crate::sourceview::SourceView;
+use crate::types::{DecodedMap, RewriteOptions, SourceMap, SourceMapLike};
 use crate::utils::greatest_lower_bound;
 use crate::vlq::parse_vlq_segment_into;
 use crate::Token;
@@ -51,6 +52,37 @@ impl DerefMut for SourceMapHermes {
     }
 }
 
+impl SourceMapLike for SourceMapHermes {
+    fn lookup_token(&self, line: u32, col: u32) -> Option<Token<'_>> {
+        self.sm.lookup_token(line, col)
+    }
+
+    fn tokens(&self) -> Box<dyn Iterator<Item = Token<'_>> + '_> {
+        Box::new(self.sm.tokens())
+    }
+
+    fn sources(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        Box::new(self.sm.sources())
+    }
+
+    fn source_contents(&self) -> Box<dyn Iterator<Item = Option<&str>> + '_> {
+        Box::new(self.sm.source_contents())
+    }
+
+    /// Resolves the function name from the embedded scope information.
+    ///
+    /// The minified name and source view are not needed for this.
+    fn original_function_name(
+        &self,
+        line: u32,
+        col: u32,
+        _minified_name: Option<&str>,
+        _source_view: Option<&SourceView<'_>>,
+    ) -> Option<&str> {
+        self.get_scope_for_token(self.sm.lookup_token(line, col)?)
+    }
+}
+
 impl Encodable for SourceMapHermes {
     fn as_raw_sourcemap(&self) -> RawSourceMap {
         // TODO: need to serialize the `HermesFunctionMap` mappings
@@ -90,6 +122,20 @@ impl SourceMapHermes {
         encode(self, w)
     }
 
+    /// Writes a sourcemap into a writer with the given options.
+    ///
+    /// See [`SourceMap::to_writer_with`](struct.SourceMap.html#method.to_writer_with)
+    pub fn to_writer_with<W: Write>(&self, w: W, options: &EncodeOptions<'_>) -> Result<()> {
+        encode_with(self, w, options)
+    }
+
+    /// Encodes the sourcemap as a base64 data URL.
+    ///
+    /// See [`SourceMap::to_data_url`](struct.SourceMap.html#method.to_data_url)
+    pub fn to_data_url(&self) -> Result<String> {
+        encode_data_url(self)
+    }
+
     /// Given a bytecode offset, this will find the enclosing scopes function
     /// name.
     pub fn get_original_function_name(&self, bytecode_offset: u32) -> Option<&str> {
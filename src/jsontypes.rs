@@ -1,5 +1,6 @@
+use debugid::DebugId;
 use serde::de::IgnoredAny;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
 #[derive(Serialize, Deserialize)]
@@ -49,6 +50,29 @@ pub struct RawSourceMap {
     pub x_metro_module_paths: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub x_facebook_sources: FacebookSources,
+    #[serde(
+        rename = "debugId",
+        alias = "debug_id",
+        default,
+        deserialize_with = "deserialize_debug_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub debug_id: Option<DebugId>,
+}
+
+/// Deserializes an optional debug ID.
+///
+/// Values that are not valid debug IDs are treated as missing as the field
+/// is not part of the specification and sourcemaps should not fail to parse
+/// because of it.
+pub fn deserialize_debug_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DebugId>, D::Error> {
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value
+        .as_ref()
+        .and_then(Value::as_str)
+        .and_then(|s| s.parse().ok()))
 }
 
 #[derive(Deserialize)]
@@ -38,12 +38,21 @@
 //! println!("token: {}", token);
 //! ```
 //!
+//! Maps that are loaded over and over again can be converted into a binary
+//! format with the [`cache`] module which can be used without parsing.
+//!
 //! # Features
 //!
 //! Functionality of the crate can be turned on and off by feature flags.  This is the
 //! current list of feature flags:
 //!
 //! * `ram_bundle`: turns on RAM bundle support
+//! * `mmap`: memory maps indexed RAM bundles that are loaded from a path
+//!   (implies `ram_bundle`)
+//! * `rayon`: decodes large sourcemaps and flattens indexes on multiple threads
+//! * `serde`: implements `Serialize` and `Deserialize` for the sourcemap types and
+//!   `OwnedToken`
+//! * `artifact_bundle`: turns on reading and writing of artifact bundles
 //!
 #[warn(missing_docs)]
 mod macros;
@@ -51,17 +60,25 @@ mod macros;
 pub use crate::builder::SourceMapBuilder;
 pub use crate::decoder::{decode, decode_data_url, decode_slice};
 pub use crate::detector::{
-    is_sourcemap, is_sourcemap_slice, locate_sourcemap_reference, locate_sourcemap_reference_slice,
-    SourceMapRef,
+    append_sourcemap_reference, is_sourcemap, is_sourcemap_slice, locate_debug_id,
+    locate_source_references, locate_source_url, locate_sourcemap_comment,
+    locate_sourcemap_reference, locate_sourcemap_reference_slice, replace_sourcemap_reference,
+    sourcemap_comment_candidates, strip_sourcemap_reference, SourceKind, SourceMapComment,
+    SourceMapCommentIter, SourceMapRef, SourceReferences,
 };
+pub use crate::encoder::{EncodeOptions, MissingSourceContents};
 pub use crate::errors::{Error, Result};
 pub use crate::hermes::SourceMapHermes;
-pub use crate::sourceview::SourceView;
+pub use crate::sourceview::{
+    SourceContext, SourceView, CONTEXT_TRUNCATION_MARKER, MAX_CONTEXT_LINE_WIDTH,
+};
 pub use crate::types::{
-    DecodedMap, IndexIter, NameIter, RawToken, RewriteOptions, SourceContentsIter, SourceIter,
-    SourceMap, SourceMapIndex, SourceMapSection, SourceMapSectionIter, Token, TokenIter,
+    Bias, DecodedMap, IndexIter, LookupOptions, NameIter, OwnedToken, RawToken, RewriteOptions,
+    SourceContentsIter, SourceIter, SourceMap, SourceMapIndex, SourceMapLike, SourceMapSection,
+    SourceMapSectionIter, Token, TokenIter,
 };
 pub use crate::utils::make_relative_path;
+pub use debugid::DebugId;
 
 mod builder;
 mod decoder;
@@ -71,10 +88,21 @@ mod errors;
 mod hermes;
 mod js_identifiers;
 mod jsontypes;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "serde")]
+mod serde_impls;
 mod sourceview;
 mod types;
 mod utils;
 
+#[cfg(feature = "artifact_bundle")]
+pub mod artifact_bundle;
+pub mod cache;
+pub mod debug_id;
+#[cfg(any(unix, windows, target_os = "redox"))]
+pub mod discovery;
 #[cfg(feature = "ram_bundle")]
 pub mod ram_bundle;
+pub mod stacktrace;
 pub mod vlq;
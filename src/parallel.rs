@@ -0,0 +1,337 @@
+//! Multi-threaded decoding and flattening for the `rayon` feature.
+//!
+//! Everything in here produces exactly the same results (including errors) as
+//! the serial implementations.
+use std::borrow::Cow;
+
+use rayon::prelude::*;
+
+use crate::builder::SourceMapBuilder;
+use crate::decoder::decode_raw_mappings;
+use crate::errors::{Error, Result};
+use crate::jsontypes::{RawSection, RawSourceMap};
+use crate::types::{DecodedMap, RawToken, SourceMap, SourceMapIndex};
+use crate::vlq::{MappingsDecoder, MappingsState};
+
+/// Mappings shorter than this are decoded on the current thread.
+pub const PARALLEL_DECODE_THRESHOLD: usize = 1 << 20;
+
+/// Indexes with fewer tokens than this are flattened on the current thread.
+pub const PARALLEL_FLATTEN_THRESHOLD: usize = 1 << 16;
+
+/// The minimal size of the line ranges that are handed to worker threads.
+const MIN_CHUNK_SIZE: usize = 1 << 16;
+
+/// Decodes the mappings of a regular sourcemap on multiple threads.
+pub fn decode_mappings(
+    mappings: &str,
+    source_count: u32,
+    name_count: u32,
+) -> Result<Vec<RawToken>> {
+    let chunk_size = (mappings.len() / (rayon::current_num_threads() * 4)).max(MIN_CHUNK_SIZE);
+    decode_mappings_chunked(mappings, source_count, name_count, chunk_size)
+}
+
+/// Splits mappings into ranges of whole lines of roughly `chunk_size` bytes.
+///
+/// Every range but the last one ends with the `;` that terminates its last
+/// line.
+fn split_mappings(mappings: &str, chunk_size: usize) -> Vec<&str> {
+    let bytes = mappings.as_bytes();
+    let mut rv = vec![];
+    let mut start = 0;
+
+    while start < bytes.len() {
+        let end = (start + chunk_size).min(bytes.len());
+        let end = match bytes[end..].iter().position(|&b| b == b';') {
+            Some(pos) => end + pos + 1,
+            None => bytes.len(),
+        };
+        rv.push(&mappings[start..end]);
+        start = end;
+    }
+
+    rv
+}
+
+/// Decodes mappings in two parallel passes.
+///
+/// VLQ values are relative to the previous segment, so a range of lines can
+/// only be decoded once the absolute state at its start is known.  The first
+/// pass decodes every range from a zeroed state which yields the state
+/// change over the range.  A prefix sum over these changes gives the start
+/// state of every range, and the second pass decodes the ranges for real.
+fn decode_mappings_chunked(
+    mappings: &str,
+    source_count: u32,
+    name_count: u32,
+    chunk_size: usize,
+) -> Result<Vec<RawToken>> {
+    let chunks = split_mappings(mappings, chunk_size);
+
+    let deltas: Vec<Result<MappingsState>> = chunks
+        .par_iter()
+        .map(|chunk| {
+            let mut decoder = MappingsDecoder::new(chunk);
+            for token in &mut decoder {
+                token?;
+            }
+            Ok(decoder.state())
+        })
+        .collect();
+
+    // Ranges after the first broken one are never reached by the serial
+    // decoder, so they are skipped.
+    let mut states = Vec::with_capacity(chunks.len());
+    let mut state = MappingsState::default();
+    for delta in deltas {
+        states.push(state);
+        match delta {
+            Ok(delta) => state = state.advance(delta),
+            Err(_) => break,
+        }
+    }
+
+    let results: Vec<Result<Vec<RawToken>>> = chunks[..states.len()]
+        .par_iter()
+        .zip(states.par_iter())
+        .map(|(chunk, state)| {
+            MappingsDecoder::new(chunk)
+                .with_state(*state)
+                .check_references(source_count, name_count)
+                .collect()
+        })
+        .collect();
+
+    let mut tokens =
+        Vec::with_capacity(results.iter().map(|r| r.as_ref().map_or(0, Vec::len)).sum());
+    for result in results {
+        tokens.extend(result?);
+    }
+    Ok(tokens)
+}
+
+/// Pre-decodes the mappings of all plain sections of an index.
+///
+/// Only sections whose map is a regular sourcemap are decoded here, the
+/// others are left to the serial decoder.  So are all sections if their
+/// mappings are shorter than `PARALLEL_DECODE_THRESHOLD` in total, in which
+/// case the result is empty.
+pub fn decode_section_mappings(sections: &[RawSection]) -> Vec<Option<Result<Vec<RawToken>>>> {
+    fn plain_map(section: &RawSection) -> Option<&RawSourceMap> {
+        section
+            .map
+            .as_deref()
+            .filter(|map| map.sections.is_none() && map.x_facebook_sources.is_none())
+    }
+
+    let mappings_len: usize = sections
+        .iter()
+        .filter_map(plain_map)
+        .map(|map| map.mappings.as_ref().map_or(0, String::len))
+        .sum();
+    if mappings_len < PARALLEL_DECODE_THRESHOLD || rayon::current_num_threads() <= 1 {
+        return vec![];
+    }
+
+    sections
+        .par_iter()
+        .map(|section| plain_map(section).map(decode_raw_mappings))
+        .collect()
+}
+
+/// The token data of a section that is needed to flatten it.
+struct SectionTokens<'a> {
+    tokens: &'a [RawToken],
+    source_count: u32,
+    name_count: u32,
+    offset: (u32, u32),
+}
+
+/// Sources and names of a section in the order they are first referenced.
+struct SectionRefs {
+    sources: Vec<u32>,
+    names: Vec<u32>,
+}
+
+fn collect_refs(section: &SectionTokens<'_>) -> SectionRefs {
+    let mut seen_sources = vec![false; section.source_count as usize];
+    let mut seen_names = vec![false; section.name_count as usize];
+    let mut rv = SectionRefs {
+        sources: vec![],
+        names: vec![],
+    };
+
+    for token in section.tokens {
+        if token.src_id < section.source_count && !seen_sources[token.src_id as usize] {
+            seen_sources[token.src_id as usize] = true;
+            rv.sources.push(token.src_id);
+        }
+        if token.name_id < section.name_count && !seen_names[token.name_id as usize] {
+            seen_names[token.name_id as usize] = true;
+            rv.names.push(token.name_id);
+        }
+    }
+
+    rv
+}
+
+/// Returns the number of tokens in all sections of an index.
+pub fn index_token_count(smi: &SourceMapIndex) -> usize {
+    smi.sections()
+        .filter_map(|section| section.get_sourcemap())
+        .map(|map| match map {
+            DecodedMap::Regular(sm) => sm.get_token_count() as usize,
+            DecodedMap::Index(idx) => index_token_count(idx),
+            DecodedMap::Hermes(smh) => smh.get_token_count() as usize,
+        })
+        .sum()
+}
+
+/// Flattens a sourcemap index using multiple threads.
+///
+/// Sections are scanned in parallel.  Sources and names are then registered
+/// in the same order the serial implementation encounters them so that IDs
+/// line up, and finally all tokens are rewritten in parallel.
+pub fn flatten(smi: &SourceMapIndex) -> Result<SourceMap> {
+    let mut maps = Vec::with_capacity(smi.get_section_count() as usize);
+    for section in smi.sections() {
+        maps.push(match section.get_sourcemap() {
+            Some(DecodedMap::Regular(sm)) => Cow::Borrowed(sm),
+            Some(DecodedMap::Index(idx)) => Cow::Owned(idx.flatten()?),
+            Some(DecodedMap::Hermes(smh)) => Cow::Borrowed(&smh.sm),
+            None => {
+                return Err(Error::CannotFlatten(format!(
+                    "Section has an unresolved \
+                     sourcemap: {}",
+                    section.get_url().unwrap_or("<unknown url>")
+                )));
+            }
+        });
+    }
+
+    let sections: Vec<SectionTokens<'_>> = maps
+        .iter()
+        .zip(smi.sections())
+        .map(|(map, section)| SectionTokens {
+            tokens: map.raw_tokens(),
+            source_count: map.get_source_count(),
+            name_count: map.get_name_count(),
+            offset: section.get_offset(),
+        })
+        .collect();
+
+    let refs: Vec<SectionRefs> = sections.par_iter().map(collect_refs).collect();
+
+    let mut builder = SourceMapBuilder::new(smi.get_file());
+    let mut remaps = Vec::with_capacity(maps.len());
+    for (map, refs) in maps.iter().zip(refs.iter()) {
+        let mut source_remap = vec![!0; map.get_source_count() as usize];
+        for &id in &refs.sources {
+            let new_id = builder.add_source(map.get_source(id).unwrap_or_default());
+            source_remap[id as usize] = new_id;
+            if !builder.has_source_contents(new_id) {
+                builder.set_source_contents(new_id, map.get_source_contents(id));
+            }
+        }
+        let mut name_remap = vec![!0; map.get_name_count() as usize];
+        for &id in &refs.names {
+            name_remap[id as usize] = builder.add_name(map.get_name(id).unwrap_or_default());
+        }
+        remaps.push((source_remap, name_remap));
+    }
+
+    let tokens: Vec<Vec<RawToken>> = sections
+        .par_iter()
+        .zip(remaps.par_iter())
+        .map(|(section, (source_remap, name_remap))| {
+            let (off_line, off_col) = section.offset;
+            section
+                .tokens
+                .iter()
+                .map(|token| RawToken {
+                    dst_line: token.dst_line + off_line,
+                    dst_col: token.dst_col + off_col,
+                    src_line: token.src_line,
+                    src_col: token.src_col,
+                    src_id: source_remap
+                        .get(token.src_id as usize)
+                        .copied()
+                        .unwrap_or(!0),
+                    name_id: name_remap
+                        .get(token.name_id as usize)
+                        .copied()
+                        .unwrap_or(!0),
+                })
+                .collect()
+        })
+        .collect();
+
+    for raw in tokens.into_iter().flatten() {
+        builder.add_raw(
+            raw.dst_line,
+            raw.dst_col,
+            raw.src_line,
+            raw.src_col,
+            Some(raw.src_id).filter(|&id| id != !0),
+            Some(raw.name_id).filter(|&id| id != !0),
+        );
+    }
+
+    Ok(builder.into_sourcemap())
+}
+
+#[test]
+fn test_decode_mappings_chunked() {
+    let mappings = "AAAA,GAAIA,GAAI,EACR;;IAAIA,GAAK,EAAG;CACVC,MAAM;;;ACAA,CAAC;AAAA";
+    let serial: Vec<_> = MappingsDecoder::new(mappings)
+        .collect::<Result<_>>()
+        .unwrap();
+    for chunk_size in 1..mappings.len() + 2 {
+        let parallel = decode_mappings_chunked(mappings, 2, 3, chunk_size).unwrap();
+        assert_eq!(parallel, serial, "chunk size {chunk_size}");
+    }
+
+    // the first error in serial order wins
+    let mappings = "AAAA;ACAA;AAAA,AA;AAAAC";
+    for chunk_size in 1..mappings.len() + 2 {
+        match decode_mappings_chunked(mappings, 1, 0, chunk_size) {
+            Err(Error::BadSourceReference(1)) => {}
+            e => panic!("unexpected result {:?} for chunk size {}", e, chunk_size),
+        }
+    }
+}
+
+#[test]
+fn test_flatten_matches_serial() {
+    let input =
+        std::fs::read("tests/fixtures/ram_bundle/indexed_bundle_1/basic.jsbundle.map").unwrap();
+    let smi = SourceMapIndex::from_slice(&input).unwrap();
+
+    let mut serial = vec![];
+    smi.flatten_serial()
+        .unwrap()
+        .to_writer(&mut serial)
+        .unwrap();
+    let mut parallel = vec![];
+    flatten(&smi).unwrap().to_writer(&mut parallel).unwrap();
+    assert_eq!(
+        String::from_utf8(parallel).unwrap(),
+        String::from_utf8(serial).unwrap()
+    );
+}
+
+#[test]
+fn test_small_index_sections_decoded_serially() {
+    let input = br#"{
+        "version": 3,
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "map": {"version": 3, "sources": ["a.js"], "names": [], "mappings": "AAAA"}
+            }
+        ]
+    }"#;
+    let rsm: RawSourceMap = serde_json::from_slice(input).unwrap();
+    assert!(decode_section_mappings(&rsm.sections.unwrap()).is_empty());
+}
@@ -5,13 +5,16 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::ops::Range;
-use std::path::Path;
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+use std::sync::OnceLock;
 
 use crate::builder::SourceMapBuilder;
 use crate::errors::{Error, Result};
 use crate::sourceview::SourceView;
-use crate::types::{SourceMap, SourceMapIndex};
+use crate::types::{SourceMap, SourceMapIndex, SourceMapSection, Token};
 
 /// Magic number for RAM bundles
 pub const RAM_BUNDLE_MAGIC: u32 = 0xFB0B_D1E5;
@@ -124,20 +127,42 @@ impl<'a> RamBundle<'a> {
     /// Parses an indexed RAM bundle from the given slice
     pub fn parse_indexed_from_slice(bytes: &'a [u8]) -> Result<Self> {
         Ok(RamBundle {
-            repr: RamBundleImpl::Indexed(IndexedRamBundle::parse(Cow::Borrowed(bytes))?),
+            repr: RamBundleImpl::Indexed(IndexedRamBundle::parse(BundleBytes::Cow(
+                Cow::Borrowed(bytes),
+            ))?),
         })
     }
 
     /// Parses an indexed RAM bundle from the given vector
     pub fn parse_indexed_from_vec(bytes: Vec<u8>) -> Result<Self> {
         Ok(RamBundle {
-            repr: RamBundleImpl::Indexed(IndexedRamBundle::parse(Cow::Owned(bytes))?),
+            repr: RamBundleImpl::Indexed(IndexedRamBundle::parse(BundleBytes::Cow(Cow::Owned(
+                bytes,
+            )))?),
         })
     }
 
     /// Creates a new indexed RAM bundle from the file path
+    ///
+    /// With the `mmap` feature enabled the file is memory mapped instead of
+    /// being read into memory.
     pub fn parse_indexed_from_path(path: &Path) -> Result<Self> {
-        RamBundle::parse_indexed_from_vec(fs::read(path)?)
+        #[cfg(feature = "mmap")]
+        {
+            let file = File::open(path)?;
+            // Safety: the mapping is read-only.  As with any memory map the
+            // file must not be truncated while the bundle is alive.
+            let mmap = unsafe { memmap2::Mmap::map(&file)? };
+            Ok(RamBundle {
+                repr: RamBundleImpl::Indexed(IndexedRamBundle::parse(BundleBytes::Mmap(
+                    Arc::new(mmap),
+                ))?),
+            })
+        }
+        #[cfg(not(feature = "mmap"))]
+        {
+            RamBundle::parse_indexed_from_vec(fs::read(path)?)
+        }
     }
 
     /// Creates a file (unbundle) RAM bundle from the path
@@ -145,10 +170,20 @@ impl<'a> RamBundle<'a> {
     /// The provided path should point to a javascript file, that serves
     /// as an entry point (startup code) for the app. The modules are stored in js-modules/
     /// directory, next to the entry point. The js-modules/ directory must ONLY contain
-    /// files with integer names and the ".js" file suffix, along with the UNBUNDLE magic file.
+    /// module files named as described in `parse_module_file_name`, along with the
+    /// UNBUNDLE magic file.  Modules of other segments (`seg-<segment_id>_<module_id>.js`) are skipped, see
+    /// `parse_unbundle_segment_from_path`.
     pub fn parse_unbundle_from_path(bundle_path: &Path) -> Result<Self> {
+        RamBundle::parse_unbundle_segment_from_path(bundle_path, MAIN_SEGMENT_ID)
+    }
+
+    /// Creates a RAM bundle from the modules of one segment of a file RAM bundle
+    ///
+    /// The path points to the startup code of the main bundle.  Only the main
+    /// segment has startup code, for all other segments it is empty.
+    pub fn parse_unbundle_segment_from_path(bundle_path: &Path, segment_id: u32) -> Result<Self> {
         Ok(RamBundle {
-            repr: RamBundleImpl::Unbundle(UnbundleRamBundle::parse(bundle_path)?),
+            repr: RamBundleImpl::Unbundle(UnbundleRamBundle::parse(bundle_path, segment_id)?),
         })
     }
 
@@ -161,7 +196,7 @@ impl<'a> RamBundle<'a> {
     }
 
     /// Looks up a module by ID in the bundle
-    pub fn get_module(&self, id: usize) -> Result<Option<RamBundleModule>> {
+    pub fn get_module(&self, id: usize) -> Result<Option<RamBundleModule<'_>>> {
         match self.repr {
             RamBundleImpl::Indexed(ref indexed) => indexed.get_module(id),
             RamBundleImpl::Unbundle(ref file) => file.get_module(id),
@@ -184,7 +219,7 @@ impl<'a> RamBundle<'a> {
         }
     }
     /// Returns an iterator over all modules in the bundle
-    pub fn iter_modules(&self) -> RamBundleModuleIter {
+    pub fn iter_modules(&self) -> RamBundleModuleIter<'_> {
         RamBundleModuleIter {
             range: 0..self.module_count(),
             ram_bundle: self,
@@ -192,16 +227,59 @@ impl<'a> RamBundle<'a> {
     }
 }
 
-/// Filename must be made of ascii-only digits and the .js extension
-/// Anything else errors with `Error::InvalidRamBundleIndex`
-fn js_filename_to_index_strict(filename: &str) -> Result<usize> {
-    match filename.strip_suffix(".js") {
-        Some(basename) => basename
-            .parse::<usize>()
-            .or(Err(Error::InvalidRamBundleIndex)),
-        None => Err(Error::InvalidRamBundleIndex),
+/// Returns the module files in the js-modules directory of a file RAM bundle
+///
+/// Module file names must follow `parse_module_file_name`, anything else
+/// errors with `Error::InvalidRamBundleIndex`.
+fn read_unbundle_modules(bundle_path: &Path) -> Result<Vec<(u32, usize, PathBuf)>> {
+    if !is_unbundle_path(bundle_path) {
+        return Err(Error::NotARamBundle);
+    }
+
+    let bundle_dir = match bundle_path.parent() {
+        Some(dir) => dir,
+        None => return Err(Error::NotARamBundle),
+    };
+
+    let mut rv = vec![];
+    for entry in bundle_dir.join(JS_MODULES_DIR_NAME).read_dir()? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let filename_os = path.file_name().unwrap();
+        let filename: &str = &filename_os.to_string_lossy();
+        if filename == "UNBUNDLE" {
+            continue;
+        }
+        let (segment_id, module_id) =
+            parse_module_file_name(filename).ok_or(Error::InvalidRamBundleIndex)?;
+        rv.push((segment_id, module_id, path));
+    }
+    Ok(rv)
+}
+
+/// A module file of a file RAM bundle
+///
+/// The contents are only read from disk the first time the module is requested.
+#[derive(Debug, Clone)]
+struct UnbundleModule {
+    path: PathBuf,
+    data: OnceLock<Vec<u8>>,
+}
+
+impl UnbundleModule {
+    fn data(&self) -> Result<&[u8]> {
+        if let Some(data) = self.data.get() {
+            return Ok(data);
+        }
+        let data = fs::read(&self.path)?;
+        Ok(self.data.get_or_init(|| data))
     }
 }
+
 /// Represents a file RAM bundle
 ///
 /// This RAM bundle type is mostly used on Android.
@@ -209,44 +287,35 @@ fn js_filename_to_index_strict(filename: &str) -> Result<usize> {
 struct UnbundleRamBundle {
     startup_code: Vec<u8>,
     module_count: usize,
-    modules: BTreeMap<usize, Vec<u8>>,
+    modules: BTreeMap<usize, UnbundleModule>,
 }
 
 impl UnbundleRamBundle {
-    pub fn parse(bundle_path: &Path) -> Result<Self> {
-        if !is_unbundle_path(bundle_path) {
-            return Err(Error::NotARamBundle);
-        }
-
-        let bundle_dir = match bundle_path.parent() {
-            Some(dir) => dir,
-            None => return Err(Error::NotARamBundle),
+    pub fn parse(bundle_path: &Path, segment_id: u32) -> Result<Self> {
+        let files = read_unbundle_modules(bundle_path)?;
+        let startup_code = if segment_id == MAIN_SEGMENT_ID {
+            fs::read(bundle_path)?
+        } else {
+            vec![]
         };
-
-        let startup_code = fs::read(bundle_path)?;
         let mut max_module_id = 0;
-        let mut modules: BTreeMap<usize, Vec<u8>> = Default::default();
-
-        let js_modules_dir = bundle_dir.join(JS_MODULES_DIR_NAME);
+        let mut modules: BTreeMap<usize, UnbundleModule> = Default::default();
 
-        for entry in js_modules_dir.read_dir()? {
-            let entry = entry?;
-            if !entry.file_type()?.is_file() {
+        for (module_segment_id, module_id, path) in files {
+            if module_segment_id != segment_id {
                 continue;
             }
-
-            let path = entry.path();
-            let filename_os = path.file_name().unwrap();
-            let filename: &str = &filename_os.to_string_lossy();
-            if filename == "UNBUNDLE" {
-                continue;
-            }
-            let module_id = js_filename_to_index_strict(filename)?;
             if module_id > max_module_id {
                 max_module_id = module_id;
             }
 
-            modules.insert(module_id, fs::read(path)?);
+            modules.insert(
+                module_id,
+                UnbundleModule {
+                    path,
+                    data: OnceLock::new(),
+                },
+            );
         }
 
         Ok(UnbundleRamBundle {
@@ -267,21 +336,46 @@ impl UnbundleRamBundle {
     }
 
     /// Looks up a module by ID in the bundle
-    pub fn get_module(&self, id: usize) -> Result<Option<RamBundleModule>> {
+    pub fn get_module(&self, id: usize) -> Result<Option<RamBundleModule<'_>>> {
         match self.modules.get(&id) {
-            Some(data) => Ok(Some(RamBundleModule { id, data })),
+            Some(module) => Ok(Some(RamBundleModule {
+                id,
+                data: module.data()?,
+            })),
             None => Ok(None),
         }
     }
 }
 
+/// The bytes backing an indexed RAM bundle
+#[derive(Debug, Clone)]
+enum BundleBytes<'a> {
+    /// Borrowed or owned in-memory data
+    Cow(Cow<'a, [u8]>),
+    /// A memory mapped file
+    #[cfg(feature = "mmap")]
+    Mmap(Arc<memmap2::Mmap>),
+}
+
+impl<'a> Deref for BundleBytes<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            BundleBytes::Cow(ref bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            BundleBytes::Mmap(ref mmap) => mmap,
+        }
+    }
+}
+
 /// Represents an indexed RAM bundle
 ///
 /// Provides access to a react-native metro
 /// [RAM bundle](https://facebook.github.io/metro/docs/en/bundling).
 #[derive(Debug, Clone)]
 struct IndexedRamBundle<'a> {
-    bytes: Cow<'a, [u8]>,
+    bytes: BundleBytes<'a>,
     module_count: usize,
     startup_code_size: usize,
     startup_code_offset: usize,
@@ -289,7 +383,7 @@ struct IndexedRamBundle<'a> {
 
 impl<'a> IndexedRamBundle<'a> {
     /// Parses a RAM bundle from a given slice of bytes.
-    pub fn parse(bytes: Cow<'a, [u8]>) -> Result<Self> {
+    pub fn parse(bytes: BundleBytes<'a>) -> Result<Self> {
         let header = bytes.pread_with::<RamBundleHeader>(0, scroll::LE)?;
 
         if !header.is_valid_magic() {
@@ -320,7 +414,7 @@ impl<'a> IndexedRamBundle<'a> {
     }
 
     /// Looks up a module by ID in the bundle
-    pub fn get_module(&self, id: usize) -> Result<Option<RamBundleModule>> {
+    pub fn get_module(&self, id: usize) -> Result<Option<RamBundleModule<'_>>> {
         if id >= self.module_count {
             return Err(Error::InvalidRamBundleIndex);
         }
@@ -452,12 +546,259 @@ pub fn split_ram_bundle<'a>(
     })
 }
 
+/// The segment ID of the main bundle of a segmented RAM bundle
+pub const MAIN_SEGMENT_ID: u32 = 0;
+
+/// A single segment of a `SegmentedRamBundle`
+#[derive(Debug, Clone)]
+pub struct RamBundleSegment<'a> {
+    id: u32,
+    bundle: RamBundle<'a>,
+    sourcemap: SourceMapIndex,
+}
+
+impl<'a> RamBundleSegment<'a> {
+    /// Returns the segment ID.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the RAM bundle of this segment.
+    pub fn bundle(&self) -> &RamBundle<'a> {
+        &self.bundle
+    }
+
+    /// Returns the indexed sourcemap of this segment.
+    pub fn sourcemap(&self) -> &SourceMapIndex {
+        &self.sourcemap
+    }
+
+    /// Returns the line offset of a module in the segment's sourcemap.
+    pub fn get_module_offset(&self, module_id: usize) -> Option<u32> {
+        self.sourcemap
+            .x_facebook_offsets()?
+            .get(module_id)
+            .copied()
+            .flatten()
+    }
+
+    /// Returns the index section that covers the given module.
+    pub fn get_section(&self, module_id: usize) -> Option<&SourceMapSection> {
+        let offset = self.get_module_offset(module_id)?;
+        self.sourcemap
+            .sections()
+            .find(|section| section.get_offset_line() == offset)
+    }
+
+    /// Looks up the closest token for a position within a module.
+    ///
+    /// `line` and `col` are 0-indexed and relative to the start of the module.
+    pub fn lookup_token(&self, module_id: usize, line: u32, col: u32) -> Option<Token<'_>> {
+        let offset = self.get_module_offset(module_id)?;
+        self.sourcemap.lookup_token(offset.checked_add(line)?, col)
+    }
+}
+
+/// A RAM bundle that Metro split into a main bundle and segments
+///
+/// Metro can split an app into a main RAM bundle and additional segment
+/// bundles that are registered at runtime.  Every segment comes with its own
+/// indexed sourcemap and module IDs are only unique within a segment, so
+/// modules are addressed by `(segment_id, module_id)`.  The main bundle is
+/// always registered as segment `MAIN_SEGMENT_ID`.
+#[derive(Debug, Clone)]
+pub struct SegmentedRamBundle<'a> {
+    segments: BTreeMap<u32, RamBundleSegment<'a>>,
+}
+
+impl<'a> SegmentedRamBundle<'a> {
+    /// Creates a segmented RAM bundle from the main bundle and its sourcemap.
+    pub fn new(main_bundle: RamBundle<'a>, main_sourcemap: SourceMapIndex) -> Result<Self> {
+        let mut rv = SegmentedRamBundle {
+            segments: BTreeMap::new(),
+        };
+        rv.add_segment(MAIN_SEGMENT_ID, main_bundle, main_sourcemap)?;
+        Ok(rv)
+    }
+
+    /// Loads the main bundle and its sourcemap from the given paths.
+    ///
+    /// Both indexed and file RAM bundles are supported.
+    pub fn parse_from_paths(bundle_path: &Path, sourcemap_path: &Path) -> Result<Self> {
+        SegmentedRamBundle::new(
+            parse_ram_bundle_from_path(bundle_path, MAIN_SEGMENT_ID)?,
+            SourceMapIndex::from_reader(File::open(sourcemap_path)?)?,
+        )
+    }
+
+    /// Loads a file RAM bundle together with all of its segments.
+    ///
+    /// The segments are found by the names of their module files, see
+    /// `parse_module_file_name`.  `sourcemap_path` is called with every
+    /// segment ID, including `MAIN_SEGMENT_ID`, and returns the path of the
+    /// sourcemap of that segment.
+    pub fn parse_unbundle_from_paths<F>(bundle_path: &Path, mut sourcemap_path: F) -> Result<Self>
+    where
+        F: FnMut(u32) -> PathBuf,
+    {
+        let mut rv =
+            SegmentedRamBundle::parse_from_paths(bundle_path, &sourcemap_path(MAIN_SEGMENT_ID))?;
+        for segment_id in get_unbundle_segment_ids(bundle_path)? {
+            if segment_id != MAIN_SEGMENT_ID {
+                rv.add_segment_from_paths(segment_id, bundle_path, &sourcemap_path(segment_id))?;
+            }
+        }
+        Ok(rv)
+    }
+
+    /// Registers a segment.
+    ///
+    /// The sourcemap must be a RAM bundle sourcemap.  An already registered
+    /// segment with the same ID is replaced.
+    pub fn add_segment(
+        &mut self,
+        segment_id: u32,
+        bundle: RamBundle<'a>,
+        sourcemap: SourceMapIndex,
+    ) -> Result<()> {
+        if sourcemap.x_facebook_offsets().is_none() {
+            return Err(Error::NotARamBundle);
+        }
+        self.segments.insert(
+            segment_id,
+            RamBundleSegment {
+                id: segment_id,
+                bundle,
+                sourcemap,
+            },
+        );
+        Ok(())
+    }
+
+    /// Loads a segment bundle and its sourcemap from the given paths and
+    /// registers it.
+    ///
+    /// Indexed segment bundles are files of their own.  For file RAM bundles
+    /// `bundle_path` is the startup code of the main bundle and the modules
+    /// of the segment are loaded from its js-modules directory.
+    pub fn add_segment_from_paths(
+        &mut self,
+        segment_id: u32,
+        bundle_path: &Path,
+        sourcemap_path: &Path,
+    ) -> Result<()> {
+        self.add_segment(
+            segment_id,
+            parse_ram_bundle_from_path(bundle_path, segment_id)?,
+            SourceMapIndex::from_reader(File::open(sourcemap_path)?)?,
+        )
+    }
+
+    /// Returns the number of registered segments, including the main bundle.
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Looks up a segment by ID.
+    pub fn get_segment(&self, segment_id: u32) -> Option<&RamBundleSegment<'a>> {
+        self.segments.get(&segment_id)
+    }
+
+    /// Returns an iterator over all segments ordered by segment ID.
+    pub fn segments(&self) -> impl Iterator<Item = &RamBundleSegment<'a>> {
+        self.segments.values()
+    }
+
+    /// Looks up a module by segment and module ID.
+    pub fn get_module(
+        &self,
+        segment_id: u32,
+        module_id: usize,
+    ) -> Result<Option<RamBundleModule<'_>>> {
+        match self.get_segment(segment_id) {
+            Some(segment) => segment.bundle.get_module(module_id),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the index section that covers the given module.
+    pub fn get_section(&self, segment_id: u32, module_id: usize) -> Option<&SourceMapSection> {
+        self.get_segment(segment_id)?.get_section(module_id)
+    }
+
+    /// Looks up the closest token for a position within a module.
+    ///
+    /// `line` and `col` are 0-indexed and relative to the start of the module.
+    pub fn lookup_token(
+        &self,
+        segment_id: u32,
+        module_id: usize,
+        line: u32,
+        col: u32,
+    ) -> Option<Token<'_>> {
+        self.get_segment(segment_id)?
+            .lookup_token(module_id, line, col)
+    }
+}
+
+/// Parses a module file name into segment and module ID.
+///
+/// Metro names modules of the main bundle `<module_id>.js` and modules of
+/// other segments `seg-<segment_id>_<module_id>.js`.
+///
+/// ```
+/// # use sourcemap::ram_bundle::parse_module_file_name;
+/// assert_eq!(parse_module_file_name("3.js"), Some((0, 3)));
+/// assert_eq!(parse_module_file_name("seg-2_14.js"), Some((2, 14)));
+/// assert_eq!(parse_module_file_name("index.js"), None);
+/// ```
+pub fn parse_module_file_name(filename: &str) -> Option<(u32, usize)> {
+    let basename = filename.strip_suffix(".js")?;
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let (segment_id, module_id) = match basename.strip_prefix("seg-") {
+        Some(rest) => {
+            let (segment_id, module_id) = rest.split_once('_')?;
+            if !is_number(segment_id) {
+                return None;
+            }
+            (segment_id.parse().ok()?, module_id)
+        }
+        None => (MAIN_SEGMENT_ID, basename),
+    };
+    if !is_number(module_id) {
+        return None;
+    }
+    Some((segment_id, module_id.parse().ok()?))
+}
+
+fn parse_ram_bundle_from_path(path: &Path, segment_id: u32) -> Result<RamBundle<'static>> {
+    if is_unbundle_path(path) {
+        RamBundle::parse_unbundle_segment_from_path(path, segment_id)
+    } else {
+        RamBundle::parse_indexed_from_path(path)
+    }
+}
+
+/// Returns the IDs of all segments of a file RAM bundle
+///
+/// The main segment is always included.  The IDs are sorted.
+pub fn get_unbundle_segment_ids(bundle_path: &Path) -> Result<Vec<u32>> {
+    let mut rv: Vec<u32> = read_unbundle_modules(bundle_path)?
+        .into_iter()
+        .map(|(segment_id, _, _)| segment_id)
+        .chain(Some(MAIN_SEGMENT_ID))
+        .collect();
+    rv.sort_unstable();
+    rv.dedup();
+    Ok(rv)
+}
+
 /// Checks if the given byte slice contains an indexed RAM bundle
 pub fn is_ram_bundle_slice(slice: &[u8]) -> bool {
     slice
         .pread_with::<RamBundleHeader>(0, scroll::LE)
         .ok()
-        .map_or(false, |x| x.is_valid_magic())
+        .is_some_and(|x| x.is_valid_magic())
 }
 
 /// Returns "true" if the given path points to the startup file of a file RAM bundle
@@ -612,3 +953,73 @@ fn test_file_ram_bundle_parse() -> std::result::Result<(), Box<dyn std::error::E
 
     Ok(())
 }
+
+#[test]
+fn test_file_ram_bundle_lazy_modules() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let ram_bundle = RamBundle::parse_unbundle_from_path(Path::new(
+        "./tests/fixtures/ram_bundle/file_bundle_1/basic.bundle",
+    ))?;
+
+    let unbundle = match ram_bundle.repr {
+        RamBundleImpl::Unbundle(ref unbundle) => unbundle,
+        _ => {
+            panic!("Invalid RamBundleImpl type");
+        }
+    };
+    assert!(unbundle.modules.values().all(|m| m.data.get().is_none()));
+
+    assert!(ram_bundle.get_module(3)?.is_some());
+    assert!(unbundle.modules[&3].data.get().is_some());
+    assert!(unbundle.modules[&0].data.get().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_segmented_ram_bundle() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let bundle_dir = Path::new("./tests/fixtures/ram_bundle/segmented_file_bundle");
+    let bundle_path = bundle_dir.join("basic.bundle");
+
+    assert_eq!(get_unbundle_segment_ids(&bundle_path)?, vec![0, 1]);
+
+    // the main bundle only sees its own modules
+    let main = RamBundle::parse_unbundle_from_path(&bundle_path)?;
+    assert_eq!(main.module_count(), 2);
+    assert!(main.startup_code()?.starts_with(b"var __DEV__"));
+    let segment = RamBundle::parse_unbundle_segment_from_path(&bundle_path, 1)?;
+    assert_eq!(segment.module_count(), 3);
+    assert!(segment.startup_code()?.is_empty());
+    assert!(segment.get_module(1)?.is_none());
+
+    let bundle =
+        SegmentedRamBundle::parse_unbundle_from_paths(
+            &bundle_path,
+            |segment_id| match segment_id {
+                MAIN_SEGMENT_ID => bundle_dir.join("basic.bundle.map"),
+                _ => bundle_dir.join(format!("seg-{segment_id}.map")),
+            },
+        )?;
+    assert_eq!(bundle.segment_count(), 2);
+
+    // module 0 exists in both segments
+    let module = bundle.get_module(0, 0)?.unwrap();
+    assert!(module.data().ends_with(b"__loadSegment(1)},0,[1]);"));
+    let module = bundle.get_module(1, 0)?.unwrap();
+    assert!(module.data().ends_with(b"(41))},0,[2]);"));
+    assert!(bundle.get_module(1, 1)?.is_none());
+    assert!(bundle.get_module(2, 0)?.is_none());
+
+    assert_eq!(bundle.get_section(0, 1).unwrap().get_offset(), (2, 0));
+    assert_eq!(bundle.get_section(1, 2).unwrap().get_offset(), (1, 0));
+    assert!(bundle.get_section(1, 1).is_none());
+
+    let token = bundle.lookup_token(0, 0, 0, 40).unwrap();
+    assert_eq!(token.get_source(), Some("src/index.js"));
+    let token = bundle.lookup_token(1, 0, 0, 40).unwrap();
+    assert_eq!(token.get_source(), Some("src/lazy.js"));
+    let token = bundle.lookup_token(1, 2, 0, 40).unwrap();
+    assert_eq!(token.get_source(), Some("src/double.js"));
+    assert!(bundle.lookup_token(2, 0, 0, 40).is_none());
+
+    Ok(())
+}
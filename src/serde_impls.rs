@@ -0,0 +1,254 @@
+//! Serde support for the `serde` feature.
+//!
+//! In human readable formats sourcemaps serialize to and deserialize from
+//! the standard JSON object of the sourcemap specification.  As that object
+//! has free-form parts it needs a self-describing format.  All other formats
+//! use a typed representation with the same contents which also works with
+//! formats that are not self-describing.
+use debugid::DebugId;
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::decoder::decode_common;
+use crate::encoder::Encodable;
+use crate::errors::Error;
+use crate::hermes::SourceMapHermes;
+use crate::jsontypes::{FacebookSources, RawSection, RawSectionOffset, RawSourceMap};
+use crate::types::{DecodedMap, SourceMap, SourceMapIndex};
+
+/// A section of a `TypedSourceMap`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TypedSection {
+    line: u32,
+    column: u32,
+    url: Option<String>,
+    map: Option<Box<TypedSourceMap>>,
+}
+
+/// A `RawSourceMap` without free-form values and skipped fields.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TypedSourceMap {
+    file: Option<String>,
+    sources: Option<Vec<Option<String>>>,
+    source_root: Option<String>,
+    sources_content: Option<Vec<Option<String>>>,
+    sections: Option<Vec<TypedSection>>,
+    names: Option<Vec<String>>,
+    mappings: Option<String>,
+    x_facebook_offsets: Option<Vec<Option<u32>>>,
+    x_metro_module_paths: Option<Vec<String>>,
+    x_facebook_sources: FacebookSources,
+    debug_id: Option<DebugId>,
+}
+
+fn value_to_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+impl From<RawSourceMap> for TypedSourceMap {
+    fn from(rsm: RawSourceMap) -> TypedSourceMap {
+        TypedSourceMap {
+            file: rsm.file.map(value_to_string),
+            sources: rsm.sources,
+            source_root: rsm.source_root,
+            sources_content: rsm.sources_content,
+            sections: rsm.sections.map(|sections| {
+                sections
+                    .into_iter()
+                    .map(|section| TypedSection {
+                        line: section.offset.line,
+                        column: section.offset.column,
+                        url: section.url,
+                        map: section.map.map(|map| Box::new((*map).into())),
+                    })
+                    .collect()
+            }),
+            names: rsm
+                .names
+                .map(|names| names.into_iter().map(value_to_string).collect()),
+            mappings: rsm.mappings,
+            x_facebook_offsets: rsm.x_facebook_offsets,
+            x_metro_module_paths: rsm.x_metro_module_paths,
+            x_facebook_sources: rsm.x_facebook_sources,
+            debug_id: rsm.debug_id,
+        }
+    }
+}
+
+impl From<TypedSourceMap> for RawSourceMap {
+    fn from(tsm: TypedSourceMap) -> RawSourceMap {
+        RawSourceMap {
+            version: Some(3),
+            file: tsm.file.map(Value::String),
+            sources: tsm.sources,
+            source_root: tsm.source_root,
+            sources_content: tsm.sources_content,
+            sections: tsm.sections.map(|sections| {
+                sections
+                    .into_iter()
+                    .map(|section| RawSection {
+                        offset: RawSectionOffset {
+                            line: section.line,
+                            column: section.column,
+                        },
+                        url: section.url,
+                        map: section.map.map(|map| Box::new((*map).into())),
+                    })
+                    .collect()
+            }),
+            names: tsm
+                .names
+                .map(|names| names.into_iter().map(Value::String).collect()),
+            mappings: tsm.mappings,
+            x_facebook_offsets: tsm.x_facebook_offsets,
+            x_metro_module_paths: tsm.x_metro_module_paths,
+            x_facebook_sources: tsm.x_facebook_sources,
+            debug_id: tsm.debug_id,
+        }
+    }
+}
+
+fn serialize_raw<S: Serializer>(rsm: RawSourceMap, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        rsm.serialize(serializer)
+    } else {
+        TypedSourceMap::from(rsm).serialize(serializer)
+    }
+}
+
+fn deserialize_decoded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DecodedMap, D::Error> {
+    let rsm = if deserializer.is_human_readable() {
+        RawSourceMap::deserialize(deserializer)?
+    } else {
+        TypedSourceMap::deserialize(deserializer)?.into()
+    };
+    decode_common(rsm).map_err(D::Error::custom)
+}
+
+macro_rules! impl_serde {
+    ($ty:ty, $pattern:pat => $value:expr) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize_raw(self.as_raw_sourcemap(), serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                match deserialize_decoded(deserializer)? {
+                    $pattern => Ok($value),
+                    #[allow(unreachable_patterns)]
+                    _ => Err(D::Error::custom(Error::IncompatibleSourceMap)),
+                }
+            }
+        }
+    };
+}
+
+impl_serde!(SourceMap, DecodedMap::Regular(sm) => sm);
+impl_serde!(SourceMapIndex, DecodedMap::Index(smi) => smi);
+impl_serde!(SourceMapHermes, DecodedMap::Hermes(smh) => smh);
+impl_serde!(DecodedMap, dm => dm);
+
+#[test]
+fn test_serde_roundtrip() {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Payload {
+        id: u32,
+        map: SourceMap,
+        other: Option<DecodedMap>,
+    }
+
+    let input = r#"{"version":3,"sources":["coolstuff.js"],"names":["x","alert"],"mappings":"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"}"#;
+    let payload: Payload =
+        serde_json::from_str(&format!(r#"{{"id":42,"map":{},"other":{}}}"#, input, input)).unwrap();
+    assert_eq!(payload.id, 42);
+    assert_eq!(
+        payload.map.lookup_token(0, 3).unwrap().to_tuple(),
+        ("coolstuff.js", 0, 4, Some("x"))
+    );
+    assert!(matches!(payload.other, Some(DecodedMap::Regular(_))));
+
+    let mut expected = vec![];
+    payload.map.to_writer(&mut expected).unwrap();
+    assert_eq!(
+        serde_json::to_string(&payload.map).unwrap().as_bytes(),
+        &expected[..]
+    );
+
+    let value = serde_json::to_value(&payload).unwrap();
+    assert_eq!(value["map"]["sources"][0], "coolstuff.js");
+    let roundtripped: Payload = serde_json::from_value(value).unwrap();
+    assert_eq!(
+        roundtripped.map.get_token_count(),
+        payload.map.get_token_count()
+    );
+}
+
+#[test]
+fn test_serde_incompatible() {
+    let input = r#"{"version":3,"sections":[]}"#;
+    assert!(serde_json::from_str::<SourceMapIndex>(input).is_ok());
+    let err = serde_json::from_str::<SourceMap>(input).unwrap_err();
+    assert_eq!(err.to_string(), "encountered incompatible sourcemap format");
+    assert!(serde_json::from_str::<SourceMap>(r#"{"version":3,"mappings":"A!"}"#).is_err());
+}
+
+#[test]
+fn test_serde_hermes_roundtrip() {
+    let input: &[_] = include_bytes!("../tests/fixtures/react-native-hermes/output.map");
+    let sm = SourceMapHermes::from_reader(input).unwrap();
+    assert_eq!(sm.get_original_function_name(11939), Some("foo"));
+
+    let json = serde_json::to_string(&sm).unwrap();
+    let roundtripped: SourceMapHermes = serde_json::from_str(&json).unwrap();
+    assert_eq!(roundtripped.get_original_function_name(11939), Some("foo"));
+    assert_eq!(
+        roundtripped.get_original_function_name(11857),
+        Some("<global>")
+    );
+    let dm: DecodedMap = serde_json::from_str(&json).unwrap();
+    assert!(matches!(dm, DecodedMap::Hermes(_)));
+}
+
+#[test]
+fn test_serde_bincode_roundtrip() {
+    let input = r#"{"version":3,"file":"min.js","sources":["coolstuff.js"],"sourcesContent":["var x = 1;"],"names":["x","alert"],"mappings":"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM","debugId":"b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"}"#;
+    let sm = SourceMap::from_slice(input.as_bytes()).unwrap();
+    let bytes = bincode::serialize(&sm).unwrap();
+    let roundtripped: SourceMap = bincode::deserialize(&bytes).unwrap();
+    let mut expected = vec![];
+    sm.to_writer(&mut expected).unwrap();
+    let mut actual = vec![];
+    roundtripped.to_writer(&mut actual).unwrap();
+    assert_eq!(actual, expected);
+
+    let input = format!(
+        r#"{{"version":3,"file":"min.js","sections":[{{"offset":{{"line":0,"column":0}},"map":{}}}]}}"#,
+        input
+    );
+    let smi = SourceMapIndex::from_slice(input.as_bytes()).unwrap();
+    let bytes = bincode::serialize(&smi).unwrap();
+    let roundtripped: DecodedMap = bincode::deserialize(&bytes).unwrap();
+    match roundtripped {
+        DecodedMap::Index(ref roundtripped) => {
+            assert_eq!(roundtripped.get_file(), Some("min.js"));
+            assert_eq!(
+                roundtripped.lookup_token(0, 3).unwrap().to_tuple(),
+                ("coolstuff.js", 0, 4, Some("x"))
+            );
+        }
+        _ => panic!("expected a sourcemap index"),
+    }
+
+    let input: &[_] = include_bytes!("../tests/fixtures/react-native-hermes/output.map");
+    let sm = SourceMapHermes::from_reader(input).unwrap();
+    let bytes = bincode::serialize(&sm).unwrap();
+    let roundtripped: SourceMapHermes = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(roundtripped.get_original_function_name(11939), Some("foo"));
+    assert_eq!(roundtripped.get_token_count(), sm.get_token_count());
+}
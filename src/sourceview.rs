@@ -1,12 +1,14 @@
 use std::borrow::Cow;
-use std::cell::RefCell;
 use std::fmt;
-use std::slice;
-use std::str;
+use std::ops::Range;
+use std::sync::OnceLock;
 
 use if_chain::if_chain;
 
-use crate::detector::{locate_sourcemap_reference_slice, SourceMapRef};
+use crate::detector::{
+    locate_source_references, locate_source_url, locate_sourcemap_comment,
+    locate_sourcemap_reference_slice, SourceMapComment, SourceMapRef, SourceReferences,
+};
 use crate::errors::Result;
 use crate::js_identifiers::{get_javascript_token, is_valid_javascript_identifier};
 use crate::types::{idx_from_token, sourcemap_from_token, Token};
@@ -18,7 +20,6 @@ where
 {
     sv: &'view SourceView<'viewbase>,
     token: Option<Token<'map>>,
-    source_line: Option<(&'view str, usize, usize, usize)>,
 }
 
 impl<'view, 'viewbase, 'map> Iterator for RevTokenIter<'view, 'viewbase, 'map>
@@ -41,68 +42,15 @@ where
             self.token = sm.get_token(idx - 1);
         }
 
-        // if we are going to the same line as we did last iteration, we don't have to scan
-        // up to it again.  For normal sourcemaps this should mean we only ever go to the
-        // line once.
-        let (source_line, last_char_offset, last_byte_offset) = if_chain! {
-            if let Some((source_line, dst_line, last_char_offset,
-                         last_byte_offset)) = self.source_line;
-
-            if dst_line == token.get_dst_line() as usize;
-            then {
-                (source_line, last_char_offset, last_byte_offset)
-            } else {
-                if let Some(source_line) = self.sv.get_line(token.get_dst_line()) {
-                    (source_line, !0, !0)
-                } else {
-                    // if we can't find the line, return am empty one
-                    ("", !0, !0)
-                }
-            }
-        };
+        // if we can't find the line, we use an empty one
+        let source_line = self.sv.get_line(token.get_dst_line()).unwrap_or("");
+        let byte_offset = self
+            .sv
+            .line_index(token.get_dst_line())
+            .and_then(|index| index.utf16_to_byte(token.get_dst_col()))
+            .map_or(source_line.len(), |(off, _)| off);
 
-        // find the byte offset where our token starts
-        let byte_offset = if last_byte_offset == !0 {
-            let mut off = 0;
-            let mut idx = 0;
-            for c in source_line.chars() {
-                if idx >= token.get_dst_col() as usize {
-                    break;
-                }
-                off += c.len_utf8();
-                idx += c.len_utf16();
-            }
-            off
-        } else {
-            let chars_to_move = last_char_offset - token.get_dst_col() as usize;
-            let mut new_offset = last_byte_offset;
-            let mut idx = 0;
-            for c in source_line
-                .get(..last_byte_offset)
-                .unwrap_or("")
-                .chars()
-                .rev()
-            {
-                if idx >= chars_to_move {
-                    break;
-                }
-                new_offset -= c.len_utf8();
-                idx += c.len_utf16();
-            }
-            new_offset
-        };
-
-        // remember where we were
-        self.source_line = Some((
-            source_line,
-            token.get_dst_line() as usize,
-            token.get_dst_col() as usize,
-            byte_offset,
-        ));
-
-        // in case we run out of bounds here we reset the cache
         if byte_offset >= source_line.len() {
-            self.source_line = None;
             Some((token, None))
         } else {
             Some((
@@ -133,24 +81,95 @@ impl<'a> Iterator for Lines<'a> {
     }
 }
 
-/// Provides efficient access to minified sources.
+/// Lines of context are trimmed to this many UTF-16 code units.
+pub const MAX_CONTEXT_LINE_WIDTH: u32 = 140;
+
+/// Marks the ends of context lines that were trimmed.
+pub const CONTEXT_TRUNCATION_MARKER: &str = "{snip}";
+
+/// Lines of source code around a position.
 ///
-/// This type is used to implement fairly efficient source mapping
-/// operations.
-pub struct SourceView<'a> {
-    source: Cow<'a, str>,
-    processed_until: RefCell<usize>,
-    lines: RefCell<Vec<(*const u8, usize)>>,
+/// See [`SourceView::get_context`](struct.SourceView.html#method.get_context).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceContext {
+    pre_context: Vec<String>,
+    context_line: String,
+    post_context: Vec<String>,
 }
 
-impl<'a> Clone for SourceView<'a> {
-    fn clone(&self) -> SourceView<'a> {
-        SourceView {
-            source: self.source.clone(),
-            processed_until: RefCell::new(0),
-            lines: RefCell::new(vec![]),
+impl SourceContext {
+    /// Returns the lines before the line of the position.
+    pub fn get_pre_context(&self) -> &[String] {
+        &self.pre_context
+    }
+
+    /// Returns the line of the position.
+    pub fn get_context_line(&self) -> &str {
+        &self.context_line
+    }
+
+    /// Returns the lines after the line of the position.
+    pub fn get_post_context(&self) -> &[String] {
+        &self.post_context
+    }
+}
+
+/// Trims a line to `MAX_CONTEXT_LINE_WIDTH` around a column.
+///
+/// Widths and the column are in UTF-16 code units.  Trimmed ends get a
+/// `CONTEXT_TRUNCATION_MARKER`.
+fn trim_context_line(line: &str, col: u32) -> String {
+    let width: usize = line.chars().map(char::len_utf16).sum();
+    let max_width = MAX_CONTEXT_LINE_WIDTH as usize;
+    if width <= max_width {
+        return line.to_owned();
+    }
+
+    let start = (col as usize)
+        .saturating_sub(max_width / 2)
+        .min(width - max_width);
+    let end = start + max_width;
+
+    // characters that straddle the window are left out
+    let mut idx = 0;
+    let mut start_off = None;
+    let mut end_off = line.len();
+    for (off, c) in line.char_indices() {
+        if start_off.is_none() && idx >= start {
+            start_off = Some(off);
+        }
+        if idx + c.len_utf16() > end {
+            end_off = off;
+            break;
         }
+        idx += c.len_utf16();
+    }
+    let start_off = start_off.unwrap_or(end_off).min(end_off);
+
+    let mut rv =
+        String::with_capacity(end_off - start_off + 2 * CONTEXT_TRUNCATION_MARKER.len() + 2);
+    if start > 0 {
+        rv.push_str(CONTEXT_TRUNCATION_MARKER);
+        rv.push(' ');
+    }
+    rv.push_str(&line[start_off..end_off]);
+    if end < width {
+        rv.push(' ');
+        rv.push_str(CONTEXT_TRUNCATION_MARKER);
     }
+    rv
+}
+
+/// Provides efficient access to minified sources.
+///
+/// This type is used to implement fairly efficient source mapping
+/// operations.  The line index is built on first access and can be shared
+/// between threads.
+#[derive(Clone)]
+pub struct SourceView<'a> {
+    source: Cow<'a, str>,
+    lines: OnceLock<Vec<Range<usize>>>,
+    line_indexes: OnceLock<Vec<OnceLock<LineIndex>>>,
 }
 
 impl<'a> fmt::Debug for SourceView<'a> {
@@ -161,9 +180,135 @@ impl<'a> fmt::Debug for SourceView<'a> {
     }
 }
 
-unsafe fn make_str<'a>(tup: (*const u8, usize)) -> &'a str {
-    let (data, len) = tup;
-    str::from_utf8_unchecked(slice::from_raw_parts(data, len))
+/// Returns the byte ranges of all lines without their line terminators.
+fn index_lines(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut rv = vec![];
+    let mut start = 0;
+
+    loop {
+        let rest = &bytes[start..];
+        match rest.iter().position(|&x| x == b'\n' || x == b'\r') {
+            Some(mut idx) => {
+                rv.push(start..start + idx);
+                if rest[idx] == b'\r' && rest.get(idx + 1) == Some(&b'\n') {
+                    idx += 1;
+                }
+                start += idx + 1;
+            }
+            None => {
+                rv.push(start..bytes.len());
+                return rv;
+            }
+        }
+    }
+}
+
+/// A character of a line that is not ASCII.
+#[derive(Debug, Clone, Copy)]
+struct WideChar {
+    byte: usize,
+    utf16: u32,
+    chr: u32,
+    c: char,
+}
+
+/// Maps between the column units of a single line.
+///
+/// Only the characters that are not ASCII are recorded, everything in
+/// between them is one byte, one UTF-16 code unit and one character wide.
+/// For ASCII lines all units are the same and the index is empty.
+#[derive(Debug, Clone)]
+struct LineIndex {
+    wide: Vec<WideChar>,
+    byte_len: usize,
+    utf16_len: u32,
+    char_len: u32,
+}
+
+impl LineIndex {
+    fn new(line: &str) -> LineIndex {
+        let mut wide = vec![];
+        let mut utf16 = 0;
+        let mut chr = 0;
+        for (byte, c) in line.char_indices() {
+            if !c.is_ascii() {
+                wide.push(WideChar {
+                    byte,
+                    utf16,
+                    chr,
+                    c,
+                });
+            }
+            utf16 += c.len_utf16() as u32;
+            chr += 1;
+        }
+        LineIndex {
+            wide,
+            byte_len: line.len(),
+            utf16_len: utf16,
+            char_len: chr,
+        }
+    }
+
+    /// Returns the last wide character at or before a position.
+    fn wide_before<F: Fn(&WideChar) -> bool>(&self, f: F) -> Option<&WideChar> {
+        let idx = self.wide.partition_point(f);
+        idx.checked_sub(1).map(|idx| &self.wide[idx])
+    }
+
+    /// Converts a UTF-16 column into a byte offset.
+    ///
+    /// Columns in the middle of a surrogate pair are moved to the end of
+    /// the character, which is signalled by the `false` in the result.
+    fn utf16_to_byte(&self, col: u32) -> Option<(usize, bool)> {
+        if col > self.utf16_len {
+            return None;
+        }
+        Some(match self.wide_before(|w| w.utf16 <= col) {
+            None => (col as usize, true),
+            Some(w) => {
+                let end = w.utf16 + w.c.len_utf16() as u32;
+                if col == w.utf16 {
+                    (w.byte, true)
+                } else if col < end {
+                    (w.byte + w.c.len_utf8(), false)
+                } else {
+                    (w.byte + w.c.len_utf8() + (col - end) as usize, true)
+                }
+            }
+        })
+    }
+
+    fn byte_to_utf16(&self, byte: usize) -> Option<u32> {
+        if byte > self.byte_len {
+            return None;
+        }
+        match self.wide_before(|w| w.byte <= byte) {
+            None => Some(byte as u32),
+            Some(w) => {
+                let end = w.byte + w.c.len_utf8();
+                if byte == w.byte {
+                    Some(w.utf16)
+                } else if byte < end {
+                    None
+                } else {
+                    Some(w.utf16 + w.c.len_utf16() as u32 + (byte - end) as u32)
+                }
+            }
+        }
+    }
+
+    fn char_to_utf16(&self, col: u32) -> Option<u32> {
+        if col > self.char_len {
+            return None;
+        }
+        Some(match self.wide_before(|w| w.chr <= col) {
+            None => col,
+            Some(w) if w.chr == col => w.utf16,
+            Some(w) => w.utf16 + w.c.len_utf16() as u32 + (col - w.chr - 1),
+        })
+    }
 }
 
 impl<'a> SourceView<'a> {
@@ -171,8 +316,8 @@ impl<'a> SourceView<'a> {
     pub fn new(source: &'a str) -> SourceView<'a> {
         SourceView {
             source: Cow::Borrowed(source),
-            processed_until: RefCell::new(0),
-            lines: RefCell::new(vec![]),
+            lines: OnceLock::new(),
+            line_indexes: OnceLock::new(),
         }
     }
 
@@ -180,87 +325,95 @@ impl<'a> SourceView<'a> {
     pub fn from_string(source: String) -> SourceView<'static> {
         SourceView {
             source: Cow::Owned(source),
-            processed_until: RefCell::new(0),
-            lines: RefCell::new(vec![]),
+            lines: OnceLock::new(),
+            line_indexes: OnceLock::new(),
         }
     }
 
-    /// Returns a requested minified line.
-    pub fn get_line(&self, idx: u32) -> Option<&str> {
-        let idx = idx as usize;
-        {
-            let lines = self.lines.borrow();
-            if idx < lines.len() {
-                return Some(unsafe { make_str(lines[idx]) });
-            }
-        }
+    fn line_ranges(&self) -> &[Range<usize>] {
+        self.lines.get_or_init(|| index_lines(&self.source))
+    }
 
-        // fetched everything
-        if *self.processed_until.borrow() > self.source.len() {
-            return None;
-        }
+    /// Returns the column index of a line, building it on first access.
+    fn line_index(&self, idx: u32) -> Option<&LineIndex> {
+        let line = self.get_line(idx)?;
+        let indexes = self
+            .line_indexes
+            .get_or_init(|| (0..self.line_count()).map(|_| OnceLock::new()).collect());
+        Some(
+            indexes
+                .get(idx as usize)?
+                .get_or_init(|| LineIndex::new(line)),
+        )
+    }
 
-        let mut processed_until = self.processed_until.borrow_mut();
-        let mut lines = self.lines.borrow_mut();
-        let mut done = false;
+    /// Returns a requested minified line.
+    pub fn get_line(&self, idx: u32) -> Option<&str> {
+        let range = self.line_ranges().get(idx as usize)?;
+        self.source.get(range.clone())
+    }
 
-        while !done {
-            let rest = &self.source.as_bytes()[*processed_until..];
+    /// Returns a line slice.
+    ///
+    /// Note that columns are indexed as JavaScript WTF-16 columns.
+    pub fn get_line_slice(&self, line: u32, col: u32, span: u32) -> Option<&str> {
+        let index = self.line_index(line)?;
+        let (off, _) = index.utf16_to_byte(col)?;
+        let (off_end, _) = index.utf16_to_byte(col.checked_add(span)?)?;
+        self.get_line(line)?.get(off..off_end)
+    }
 
-            let rv = if let Some(mut idx) = rest.iter().position(|&x| x == b'\n' || x == b'\r') {
-                let rv = &rest[..idx];
-                if rest[idx] == b'\r' && rest.get(idx + 1) == Some(&b'\n') {
-                    idx += 1;
-                }
-                *processed_until += idx + 1;
-                rv
-            } else {
-                *processed_until += rest.len() + 1;
-                done = true;
-                rest
-            };
-            lines.push((rv.as_ptr(), rv.len()));
-            if let Some(&line) = lines.get(idx) {
-                return Some(unsafe { make_str(line) });
-            }
+    /// Converts a UTF-16 column of a line into a byte offset into the line.
+    ///
+    /// Sourcemap columns are in UTF-16 code units.  The column may point
+    /// right past the end of the line.  `None` is returned if the line or
+    /// column does not exist or if the column splits a surrogate pair.
+    pub fn utf16_col_to_byte(&self, line: u32, col: u32) -> Option<usize> {
+        match self.line_index(line)?.utf16_to_byte(col)? {
+            (off, true) => Some(off),
+            (_, false) => None,
         }
+    }
 
-        None
+    /// Converts a byte offset into a line into a UTF-16 column.
+    ///
+    /// `None` is returned if the line does not exist or the offset is not
+    /// on a character boundary of the line.
+    pub fn byte_to_utf16_col(&self, line: u32, byte: usize) -> Option<u32> {
+        self.line_index(line)?.byte_to_utf16(byte)
     }
 
-    /// Returns a line slice.
+    /// Converts a column in Unicode scalar values into a UTF-16 column.
     ///
-    /// Note that columns are indexed as JavaScript WTF-16 columns.
-    pub fn get_line_slice(&self, line: u32, col: u32, span: u32) -> Option<&str> {
-        self.get_line(line).and_then(|line| {
-            let mut off = 0;
-            let mut idx = 0;
-            let mut char_iter = line.chars().peekable();
-
-            while let Some(&c) = char_iter.peek() {
-                if idx >= col as usize {
-                    break;
-                }
-                char_iter.next();
-                off += c.len_utf8();
-                idx += c.len_utf16();
-            }
+    /// `None` is returned if the line or column does not exist.
+    pub fn char_col_to_utf16(&self, line: u32, col: u32) -> Option<u32> {
+        self.line_index(line)?.char_to_utf16(col)
+    }
 
-            let mut off_end = off;
-            for c in char_iter {
-                if idx >= (col + span) as usize {
-                    break;
-                }
-                off_end += c.len_utf8();
-                idx += c.len_utf16();
-            }
+    /// Converts a byte offset into the source into a line and UTF-16 column.
+    ///
+    /// Offsets at a line terminator map to the end of their line.  `None`
+    /// is returned for offsets past the end of the source, within a
+    /// `\r\n` terminator or not on a character boundary.
+    pub fn offset_to_position(&self, offset: usize) -> Option<(u32, u32)> {
+        let ranges = self.line_ranges();
+        let line = ranges
+            .partition_point(|r| r.start <= offset)
+            .checked_sub(1)?;
+        let range = &ranges[line];
+        if offset > range.end {
+            return None;
+        }
+        let col = self.byte_to_utf16_col(line as u32, offset - range.start)?;
+        Some((line as u32, col))
+    }
 
-            if idx < ((col + span) as usize) {
-                None
-            } else {
-                line.get(off..off_end)
-            }
-        })
+    /// Converts a line and UTF-16 column into a byte offset into the source.
+    ///
+    /// This is the inverse of `offset_to_position`.
+    pub fn position_to_offset(&self, line: u32, col: u32) -> Option<usize> {
+        let start = self.line_ranges().get(line as usize)?.start;
+        Some(start + self.utf16_col_to_byte(line, col)?)
     }
 
     /// Returns an iterator over all lines.
@@ -280,7 +433,6 @@ impl<'a> SourceView<'a> {
         RevTokenIter {
             sv: self,
             token: Some(token),
-            source_line: None,
         }
     }
 
@@ -319,14 +471,67 @@ impl<'a> SourceView<'a> {
 
     /// Returns the number of lines.
     pub fn line_count(&self) -> usize {
-        self.get_line(!0);
-        self.lines.borrow().len()
+        self.line_ranges().len()
+    }
+
+    /// Returns a line together with up to `before` lines before and `after`
+    /// lines after it.
+    ///
+    /// Long lines are trimmed from their start.  Use `get_context_at` to
+    /// keep a column in view, which matters for minified files that are
+    /// made of few long lines.
+    pub fn get_context(&self, line: u32, before: u32, after: u32) -> Option<SourceContext> {
+        self.get_context_at(line, 0, before, after)
+    }
+
+    /// Returns the lines around a position with long lines trimmed around
+    /// the column.
+    ///
+    /// All lines are trimmed to `MAX_CONTEXT_LINE_WIDTH` around the same
+    /// column.  Note that columns are indexed as JavaScript WTF-16 columns.
+    pub fn get_context_at(
+        &self,
+        line: u32,
+        col: u32,
+        before: u32,
+        after: u32,
+    ) -> Option<SourceContext> {
+        let context_line = trim_context_line(self.get_line(line)?, col);
+        let trimmed = |idx| self.get_line(idx).map(|line| trim_context_line(line, col));
+        Some(SourceContext {
+            pre_context: (line.saturating_sub(before)..line)
+                .filter_map(trimmed)
+                .collect(),
+            context_line,
+            post_context: (line + 1..line.saturating_add(after).saturating_add(1))
+                .map_while(trimmed)
+                .collect(),
+        })
     }
 
     /// Returns the source map reference in the source view.
     pub fn sourcemap_reference(&self) -> Result<Option<SourceMapRef>> {
         locate_sourcemap_reference_slice(self.source.as_bytes())
     }
+
+    /// Returns the source map reference comment together with its location.
+    ///
+    /// See [`locate_sourcemap_comment`](fn.locate_sourcemap_comment.html).
+    pub fn sourcemap_comment(&self) -> Option<SourceMapComment> {
+        locate_sourcemap_comment(self.source.as_bytes())
+    }
+
+    /// Returns the `sourceURL` of the source view.
+    ///
+    /// See [`locate_source_url`](fn.locate_source_url.html).
+    pub fn source_url(&self) -> Option<String> {
+        locate_source_url(self.source.as_bytes())
+    }
+
+    /// Returns the `sourceURL` together with the source map reference.
+    pub fn source_references(&self) -> SourceReferences {
+        locate_source_references(self.source.as_bytes())
+    }
 }
 
 #[test]
@@ -369,3 +574,89 @@ fn test_minified_source_view() {
     assert_eq!(view.get_line(3), Some(""));
     assert_eq!(view.get_line(4), None);
 }
+
+#[test]
+fn test_trim_context_line() {
+    assert_eq!(trim_context_line("short", 3), "short");
+
+    let line = "a".repeat(300);
+    let trimmed = trim_context_line(&line, 0);
+    assert_eq!(trimmed, format!("{} {{snip}}", "a".repeat(140)));
+    let trimmed = trim_context_line(&line, 150);
+    assert_eq!(trimmed, format!("{{snip}} {} {{snip}}", "a".repeat(140)));
+    let trimmed = trim_context_line(&line, 299);
+    assert_eq!(trimmed, format!("{{snip}} {}", "a".repeat(140)));
+
+    // surrogate pairs at the edges of the window are not split
+    let line = format!("{}{}", "a".repeat(139), "😀".repeat(10));
+    assert_eq!(
+        trim_context_line(&line, 0),
+        format!("{} {{snip}}", "a".repeat(139))
+    );
+    let line = format!("{}{}", "😀".repeat(10), "a".repeat(139));
+    assert_eq!(
+        trim_context_line(&line, 158),
+        format!("{{snip}} {}", "a".repeat(139))
+    );
+}
+
+#[test]
+fn test_get_context() {
+    let view = SourceView::new("a\nb\nc\nd\ne");
+    let context = view.get_context(2, 1, 5).unwrap();
+    assert_eq!(context.get_pre_context(), &["b".to_string()]);
+    assert_eq!(context.get_context_line(), "c");
+    assert_eq!(
+        context.get_post_context(),
+        &["d".to_string(), "e".to_string()]
+    );
+
+    let context = view.get_context(0, 3, 0).unwrap();
+    assert!(context.get_pre_context().is_empty());
+    assert!(context.get_post_context().is_empty());
+
+    assert_eq!(view.get_context(5, 1, 1), None);
+}
+
+#[test]
+fn test_utf16_conversions() {
+    let view = SourceView::new("abc👌déf\r\nblah\n");
+    assert_eq!(view.utf16_col_to_byte(0, 0), Some(0));
+    assert_eq!(view.utf16_col_to_byte(0, 3), Some(3));
+    assert_eq!(view.utf16_col_to_byte(0, 4), None);
+    assert_eq!(view.utf16_col_to_byte(0, 5), Some(7));
+    assert_eq!(view.utf16_col_to_byte(0, 7), Some(10));
+    assert_eq!(view.utf16_col_to_byte(0, 8), Some(11));
+    assert_eq!(view.utf16_col_to_byte(0, 9), None);
+    assert_eq!(view.utf16_col_to_byte(1, 4), Some(4));
+    assert_eq!(view.utf16_col_to_byte(3, 0), None);
+
+    assert_eq!(view.byte_to_utf16_col(0, 3), Some(3));
+    assert_eq!(view.byte_to_utf16_col(0, 5), None);
+    assert_eq!(view.byte_to_utf16_col(0, 7), Some(5));
+    assert_eq!(view.byte_to_utf16_col(0, 9), None);
+    assert_eq!(view.byte_to_utf16_col(0, 11), Some(8));
+    assert_eq!(view.byte_to_utf16_col(0, 12), None);
+
+    assert_eq!(view.char_col_to_utf16(0, 3), Some(3));
+    assert_eq!(view.char_col_to_utf16(0, 4), Some(5));
+    assert_eq!(view.char_col_to_utf16(0, 6), Some(7));
+    assert_eq!(view.char_col_to_utf16(0, 7), Some(8));
+    assert_eq!(view.char_col_to_utf16(0, 8), None);
+    assert_eq!(view.char_col_to_utf16(1, 2), Some(2));
+
+    assert_eq!(view.offset_to_position(0), Some((0, 0)));
+    assert_eq!(view.offset_to_position(7), Some((0, 5)));
+    assert_eq!(view.offset_to_position(11), Some((0, 8)));
+    assert_eq!(view.offset_to_position(12), None);
+    assert_eq!(view.offset_to_position(13), Some((1, 0)));
+    assert_eq!(view.offset_to_position(18), Some((2, 0)));
+    assert_eq!(view.offset_to_position(19), None);
+
+    for offset in [0, 3, 7, 10, 11, 13, 17, 18] {
+        let (line, col) = view.offset_to_position(offset).unwrap();
+        assert_eq!(view.position_to_offset(line, col), Some(offset));
+    }
+    assert_eq!(view.position_to_offset(0, 4), None);
+    assert_eq!(view.position_to_offset(1, 5), None);
+}
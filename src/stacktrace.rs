@@ -0,0 +1,491 @@
+//! Parsing and symbolication of JavaScript stack traces.
+//!
+//! The stack trace formats of V8 (Chrome, Node), SpiderMonkey (Firefox)
+//! and JavaScriptCore (Safari) are understood:
+//!
+//! ```rust
+//! use sourcemap::stacktrace::StackTrace;
+//! let trace = StackTrace::parse(
+//!     "TypeError: x is undefined\n    at foo (http://example.com/app.min.js:1:107)\n",
+//! );
+//! assert_eq!(trace.get_message(), Some("TypeError: x is undefined"));
+//! let frame = &trace.frames()[0];
+//! assert_eq!(frame.get_function(), Some("foo"));
+//! assert_eq!(frame.get_url(), Some("http://example.com/app.min.js"));
+//! assert_eq!((frame.get_line(), frame.get_column()), (Some(1), Some(107)));
+//! ```
+//!
+//! Line and column numbers are 1-based as they appear in stack traces.
+//! [`StackTrace::symbolicate`] maps all frames through sourcemaps and the
+//! result renders as a Node style stack trace.
+use std::borrow::Borrow;
+use std::fmt;
+
+use crate::sourceview::SourceView;
+use crate::types::{DecodedMap, OwnedToken};
+
+/// A single frame of a stack trace.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StackFrame {
+    function: Option<String>,
+    url: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    is_async: bool,
+    is_eval: bool,
+    is_native: bool,
+}
+
+impl StackFrame {
+    /// Returns the function name as given in the trace.
+    pub fn get_function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Returns the URL of the file.
+    ///
+    /// For code evaluated by `eval` this is the file that called `eval` and
+    /// the position is the one of that call.
+    pub fn get_url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Returns the 1-based line number.
+    pub fn get_line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Returns the 1-based column number.
+    pub fn get_column(&self) -> Option<u32> {
+        self.column
+    }
+
+    /// Returns `true` for frames of async functions that were awaited.
+    pub fn is_async(&self) -> bool {
+        self.is_async
+    }
+
+    /// Returns `true` for frames in code evaluated by `eval` or `Function`.
+    pub fn is_eval(&self) -> bool {
+        self.is_eval
+    }
+
+    /// Returns `true` for frames in native code.
+    pub fn is_native(&self) -> bool {
+        self.is_native
+    }
+}
+
+/// Splits `url:line:col` or `url:line` into its parts.
+fn parse_location(location: &str) -> (Option<String>, Option<u32>, Option<u32>) {
+    let location = location.trim();
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next().and_then(|x| x.parse().ok());
+    let middle = parts.next();
+    let rest = parts.next();
+    match (rest, middle.and_then(|x| x.parse().ok()), last) {
+        (Some(url), Some(line), Some(col)) => (Some(url.to_owned()), Some(line), Some(col)),
+        (_, None, Some(line)) => {
+            let end = location.rfind(':').unwrap_or(0);
+            (Some(location[..end].to_owned()), Some(line), None)
+        }
+        _ if location.is_empty() => (None, None, None),
+        _ => (Some(location.to_owned()), None, None),
+    }
+}
+
+/// Checks if the location of a frame without a function is a URL or path.
+fn looks_like_url(url: &str) -> bool {
+    !url.is_empty()
+        && !url.contains(char::is_whitespace)
+        && (url.contains('/') || url.contains('\\'))
+}
+
+/// Parses the location part of a V8 frame.
+fn parse_v8_location(frame: &mut StackFrame, location: &str) {
+    if let Some(origin) = location.strip_prefix("eval at ") {
+        // the innermost parentheses hold the location of the `eval` call
+        frame.is_eval = true;
+        if let Some(end) = origin.find(')') {
+            if let Some(start) = origin[..end].rfind('(') {
+                let (url, line, column) = parse_location(&origin[start + 1..end]);
+                frame.url = url;
+                frame.line = line;
+                frame.column = column;
+            }
+        }
+    } else if location == "native" {
+        frame.is_native = true;
+    } else {
+        let (url, line, column) = parse_location(location);
+        frame.url = url;
+        frame.line = line;
+        frame.column = column;
+    }
+}
+
+/// Parses a V8 frame such as `at foo (http://example.com/app.js:1:2)`.
+fn parse_v8_frame(line: &str) -> Option<StackFrame> {
+    let rest = line.strip_prefix("at ")?;
+    let mut frame = StackFrame::default();
+    let rest = match rest.strip_prefix("async ") {
+        Some(rest) => {
+            frame.is_async = true;
+            rest
+        }
+        None => rest,
+    };
+
+    match rest.find(" (") {
+        Some(idx) if rest.ends_with(')') => {
+            frame.function = Some(rest[..idx].to_owned());
+            parse_v8_location(&mut frame, &rest[idx + 2..rest.len() - 1]);
+        }
+        _ => parse_v8_location(&mut frame, rest),
+    }
+
+    // a message line can start with `at` as well
+    if frame.line.is_none()
+        && !frame.is_native
+        && !frame.is_eval
+        && frame.url.as_deref() != Some("<anonymous>")
+    {
+        return None;
+    }
+    Some(frame)
+}
+
+/// Parses a SpiderMonkey or JavaScriptCore frame such as
+/// `foo@http://example.com/app.js:1:2`.
+fn parse_gecko_frame(line: &str) -> Option<StackFrame> {
+    let mut frame = StackFrame::default();
+    let (function, location) = match line.find('@') {
+        Some(idx) => (&line[..idx], &line[idx + 1..]),
+        None if line == "[native code]" => ("", line),
+        None => {
+            // JavaScriptCore leaves out the `@` for anonymous frames.  As
+            // messages can end in `host:port` only full locations count.
+            let (url, line, column) = parse_location(line);
+            if !url.as_deref().is_some_and(looks_like_url) || column.is_none() {
+                return None;
+            }
+            frame.url = url;
+            frame.line = line;
+            frame.column = column;
+            return Some(frame);
+        }
+    };
+
+    let function = match function.strip_prefix("async*") {
+        Some(function) => {
+            frame.is_async = true;
+            function
+        }
+        None => function,
+    };
+    match function {
+        "" | "global code" => {}
+        "eval code" | "eval" => {
+            frame.is_eval = true;
+        }
+        _ => frame.function = Some(function.to_owned()),
+    }
+
+    if location == "[native code]" {
+        frame.is_native = true;
+    } else if let Some(idx) = location.find(" line ") {
+        // `url line 10 > eval:1:2`: the eval call is on line 10 of url
+        frame.is_eval = true;
+        frame.url = Some(location[..idx].to_owned());
+        let rest = &location[idx + 6..];
+        frame.line = rest[..rest.find(' ').unwrap_or(rest.len())].parse().ok();
+    } else {
+        let (url, line, column) = parse_location(location);
+        frame.url = url;
+        frame.line = line;
+        frame.column = column;
+    }
+
+    // messages can contain an `@` too
+    if frame.line.is_none() && !frame.is_native && !frame.is_eval {
+        return None;
+    }
+    Some(frame)
+}
+
+/// A parsed stack trace.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StackTrace {
+    message: Option<String>,
+    frames: Vec<StackFrame>,
+}
+
+impl StackTrace {
+    /// Parses a stack trace.
+    ///
+    /// Lines before the first frame are the error message which only V8
+    /// includes in traces.  Lines that are not understood are skipped.
+    pub fn parse(trace: &str) -> StackTrace {
+        let mut message: Vec<&str> = vec![];
+        let mut frames = vec![];
+
+        for line in trace.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let frame = if trimmed.starts_with("at ") {
+                parse_v8_frame(trimmed)
+            } else {
+                parse_gecko_frame(trimmed)
+            };
+            match frame {
+                Some(frame) => frames.push(frame),
+                None if frames.is_empty() => message.push(line),
+                None => {}
+            }
+        }
+
+        StackTrace {
+            message: if message.is_empty() {
+                None
+            } else {
+                Some(message.join("\n"))
+            },
+            frames,
+        }
+    }
+
+    /// Returns the error message if the trace has one.
+    pub fn get_message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Returns the frames, innermost first.
+    pub fn frames(&self) -> &[StackFrame] {
+        &self.frames
+    }
+
+    /// Maps all frames back to the original sources.
+    ///
+    /// The resolver is called with the URL of every frame and returns the
+    /// sourcemap of the file and, if available, the minified file itself.
+    /// Both can be returned by reference or as owned values (for instance
+    /// straight from an artifact bundle, or as `Rc` from a cache).  Original
+    /// function names can only be found for regular sourcemaps if the
+    /// minified file is given.
+    pub fn symbolicate<'s, F, M, V>(&self, mut resolver: F) -> SymbolicatedStackTrace
+    where
+        F: FnMut(&str) -> Option<(M, Option<V>)>,
+        M: Borrow<DecodedMap>,
+        V: Borrow<SourceView<'s>>,
+    {
+        let frames = self
+            .frames
+            .iter()
+            .map(|frame| symbolicate_frame(frame, &mut resolver))
+            .collect();
+        SymbolicatedStackTrace {
+            message: self.message.clone(),
+            frames,
+        }
+    }
+}
+
+fn symbolicate_frame<'s, F, M, V>(frame: &StackFrame, resolver: &mut F) -> SymbolicatedFrame
+where
+    F: FnMut(&str) -> Option<(M, Option<V>)>,
+    M: Borrow<DecodedMap>,
+    V: Borrow<SourceView<'s>>,
+{
+    let mut rv = SymbolicatedFrame {
+        frame: frame.clone(),
+        token: None,
+        function: frame.function.clone(),
+    };
+    let (url, line) = match (frame.url.as_deref(), frame.line) {
+        (Some(url), Some(line)) if line > 0 => (url, line - 1),
+        _ => return rv,
+    };
+    let col = frame.column.unwrap_or(1).saturating_sub(1);
+    let (sm, sv) = match resolver(url) {
+        Some(resolved) => resolved,
+        None => return rv,
+    };
+    let sm = sm.borrow();
+    let sv = sv.as_ref().map(Borrow::borrow);
+
+    rv.token = sm
+        .lookup_token(line, col)
+        .map(|token| token.to_owned_token());
+    if rv.token.is_some() {
+        // only the last segment of dotted names can be found in the source,
+        // V8 prefixes constructors with `new` and appends the property a
+        // method was called as
+        let minified_name = frame.function.as_deref().map(|name| {
+            let name = name.strip_prefix("new ").unwrap_or(name);
+            let name = match name.find(" [as ") {
+                Some(idx) if name.ends_with(']') => &name[..idx],
+                _ => name,
+            };
+            name.rsplit('.').next().unwrap_or(name)
+        });
+        if let Some(name) = sm.get_original_function_name(line, col, minified_name, sv) {
+            rv.function = Some(name.to_owned());
+        }
+    }
+    rv
+}
+
+/// A stack frame mapped to the original source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolicatedFrame {
+    frame: StackFrame,
+    token: Option<OwnedToken>,
+    function: Option<String>,
+}
+
+impl SymbolicatedFrame {
+    /// Returns the frame as it was in the minified trace.
+    pub fn get_raw_frame(&self) -> &StackFrame {
+        &self.frame
+    }
+
+    /// Returns the sourcemap token the frame was mapped to.
+    pub fn get_token(&self) -> Option<&OwnedToken> {
+        self.token.as_ref()
+    }
+
+    /// Returns `true` if the frame could be mapped.
+    pub fn is_mapped(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Returns the original function name or the minified one.
+    pub fn get_function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Returns the original source file or the minified URL.
+    pub fn get_source(&self) -> Option<&str> {
+        match self.token {
+            Some(ref token) => token.get_source(),
+            None => self.frame.get_url(),
+        }
+    }
+
+    /// Returns the 1-based line in the original source.
+    pub fn get_line(&self) -> Option<u32> {
+        match self.token {
+            Some(ref token) => Some(token.get_src_line() + 1),
+            None => self.frame.get_line(),
+        }
+    }
+
+    /// Returns the 1-based column in the original source.
+    pub fn get_column(&self) -> Option<u32> {
+        match self.token {
+            Some(ref token) => Some(token.get_src_col() + 1),
+            None => self.frame.get_column(),
+        }
+    }
+
+    /// Returns the line of original source code if it is available.
+    pub fn get_context_line(&self) -> Option<&str> {
+        self.token.as_ref().and_then(OwnedToken::get_context_line)
+    }
+}
+
+/// A stack trace mapped to the original sources.
+///
+/// The `Display` implementation renders the trace like Node does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolicatedStackTrace {
+    message: Option<String>,
+    frames: Vec<SymbolicatedFrame>,
+}
+
+impl SymbolicatedStackTrace {
+    /// Returns the error message if the trace has one.
+    pub fn get_message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Returns the frames, innermost first.
+    pub fn frames(&self) -> &[SymbolicatedFrame] {
+        &self.frames
+    }
+}
+
+fn write_node_frame(
+    f: &mut fmt::Formatter<'_>,
+    frame: &StackFrame,
+    function: Option<&str>,
+    location: (Option<&str>, Option<u32>, Option<u32>),
+) -> fmt::Result {
+    write!(f, "    at ")?;
+    if frame.is_async {
+        write!(f, "async ")?;
+    }
+    if let Some(function) = function {
+        write!(f, "{function} (")?;
+    }
+    match location {
+        _ if frame.is_native => write!(f, "native")?,
+        (Some(source), Some(line), Some(col)) => write!(f, "{source}:{line}:{col}")?,
+        (Some(source), Some(line), None) => write!(f, "{source}:{line}")?,
+        (Some(source), None, _) => write!(f, "{source}")?,
+        (None, _, _) => write!(f, "<anonymous>")?,
+    }
+    if function.is_some() {
+        write!(f, ")")?;
+    }
+    writeln!(f)
+}
+
+impl fmt::Display for StackTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref message) = self.message {
+            writeln!(f, "{message}")?;
+        }
+        for frame in &self.frames {
+            let location = (frame.get_url(), frame.get_line(), frame.get_column());
+            write_node_frame(f, frame, frame.get_function(), location)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SymbolicatedStackTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref message) = self.message {
+            writeln!(f, "{message}")?;
+        }
+        for frame in &self.frames {
+            let location = (frame.get_source(), frame.get_line(), frame.get_column());
+            write_node_frame(f, &frame.frame, frame.get_function(), location)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_location() {
+    assert_eq!(
+        parse_location("http://example.com/app.js:10:5"),
+        (Some("http://example.com/app.js".into()), Some(10), Some(5))
+    );
+    assert_eq!(
+        parse_location("http://example.com/app.js:10"),
+        (Some("http://example.com/app.js".into()), Some(10), None)
+    );
+    assert_eq!(
+        parse_location("http://example.com/app.js"),
+        (Some("http://example.com/app.js".into()), None, None)
+    );
+    assert_eq!(
+        parse_location("<anonymous>"),
+        (Some("<anonymous>".into()), None, None)
+    );
+    assert_eq!(parse_location(""), (None, None, None));
+}
@@ -4,13 +4,19 @@ use std::fmt;
 use std::io::{Read, Write};
 use std::path::Path;
 
+use debugid::DebugId;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::builder::SourceMapBuilder;
 use crate::decoder::{decode, decode_slice};
-use crate::encoder::encode;
+use crate::encoder::{encode, encode_data_url, encode_with, EncodeOptions};
 use crate::errors::{Error, Result};
 use crate::hermes::SourceMapHermes;
-use crate::sourceview::SourceView;
-use crate::utils::{find_common_prefix, greatest_lower_bound};
+use crate::sourceview::{SourceContext, SourceView};
+use crate::utils::{
+    find_common_prefix, greatest_lower_bound, greatest_lower_bounds, least_upper_bound,
+};
 
 /// Controls the `SourceMap::rewrite` behavior
 ///
@@ -50,6 +56,90 @@ impl<'a> Default for RewriteOptions<'a> {
     }
 }
 
+/// Controls which token a lookup resolves to if there is no exact match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bias {
+    /// The closest token before the position (this is what `lookup_token`
+    /// does).
+    GreatestLowerBound,
+    /// The closest token after the position.
+    LeastUpperBound,
+}
+
+/// Options for token lookups.
+///
+/// Default configuration:
+///
+/// * `bias`: `Bias::GreatestLowerBound`
+/// * `same_line_only`: false
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LookupOptions {
+    /// Which token to pick if there is no token at the exact position.
+    pub bias: Bias,
+    /// If enabled only tokens on the requested line are returned.
+    pub same_line_only: bool,
+}
+
+impl Default for LookupOptions {
+    fn default() -> LookupOptions {
+        LookupOptions {
+            bias: Bias::GreatestLowerBound,
+            same_line_only: false,
+        }
+    }
+}
+
+/// Common interface of all kinds of sourcemaps.
+///
+/// This is implemented by `SourceMap`, `SourceMapIndex`, `SourceMapHermes`
+/// and `DecodedMap` so that code can be generic over the kind of sourcemap
+/// instead of matching on `DecodedMap`.  The trait is object safe.
+///
+/// ```rust
+/// use sourcemap::{SourceMap, SourceMapLike};
+/// # let input: &[_] = b"{
+/// #     \"version\":3,
+/// #     \"sources\":[\"coolstuff.js\"],
+/// #     \"names\":[\"x\",\"alert\"],
+/// #     \"mappings\":\"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM\"
+/// # }";
+/// fn first_source<M: SourceMapLike>(map: &M) -> Option<&str> {
+///     map.sources().next()
+/// }
+///
+/// let sm = SourceMap::from_slice(input).unwrap();
+/// assert_eq!(first_source(&sm), Some("coolstuff.js"));
+/// ```
+pub trait SourceMapLike {
+    /// Looks up the closest token to a given 0-indexed line and column.
+    fn lookup_token(&self, line: u32, col: u32) -> Option<Token<'_>>;
+
+    /// Returns an iterator over all tokens.
+    ///
+    /// For sourcemap indexes these are the tokens of all embedded
+    /// sourcemaps, with positions relative to their section.
+    fn tokens(&self) -> Box<dyn Iterator<Item = Token<'_>> + '_>;
+
+    /// Returns an iterator over all sources.
+    fn sources(&self) -> Box<dyn Iterator<Item = &str> + '_>;
+
+    /// Returns an iterator over the contents of all sources.
+    fn source_contents(&self) -> Box<dyn Iterator<Item = Option<&str>> + '_>;
+
+    /// Resolves the original name of the function at a given location.
+    ///
+    /// Hermes sourcemaps know this from their scope information, all other
+    /// sourcemaps need the minified name of the function and the minified
+    /// source to guess it.
+    fn original_function_name(
+        &self,
+        line: u32,
+        col: u32,
+        minified_name: Option<&str>,
+        source_view: Option<&SourceView<'_>>,
+    ) -> Option<&str>;
+}
+
 /// Represents the result of a decode operation
 ///
 /// This represents either an actual sourcemap or a source map index.
@@ -81,6 +171,26 @@ impl DecodedMap {
         }
     }
 
+    /// Encodes the sourcemap as a base64 data URL.
+    ///
+    /// The result can be used as an inline `sourceMappingURL`.
+    pub fn to_data_url(&self) -> Result<String> {
+        match *self {
+            DecodedMap::Regular(ref sm) => encode_data_url(sm),
+            DecodedMap::Index(ref smi) => encode_data_url(smi),
+            DecodedMap::Hermes(ref smh) => encode_data_url(smh),
+        }
+    }
+
+    /// Writes a decoded sourcemap to a writer with the given options.
+    pub fn to_writer_with<W: Write>(&self, w: W, options: &EncodeOptions<'_>) -> Result<()> {
+        match *self {
+            DecodedMap::Regular(ref sm) => encode_with(sm, w, options),
+            DecodedMap::Index(ref smi) => encode_with(smi, w, options),
+            DecodedMap::Hermes(ref smh) => encode_with(smh, w, options),
+        }
+    }
+
     /// Shortcut to look up a token on either an index or a
     /// regular sourcemap.  This method can only be used if
     /// the contained index actually contains embedded maps
@@ -93,11 +203,83 @@ impl DecodedMap {
         }
     }
 
+    /// Returns the debug ID of the sourcemap in case there is one.
+    pub fn get_debug_id(&self) -> Option<DebugId> {
+        match *self {
+            DecodedMap::Regular(ref sm) => sm.get_debug_id(),
+            DecodedMap::Index(ref smi) => smi.get_debug_id(),
+            DecodedMap::Hermes(ref smh) => smh.get_debug_id(),
+        }
+    }
+
+    /// Sets a new value for the debug ID.
+    pub fn set_debug_id(&mut self, debug_id: Option<DebugId>) {
+        match *self {
+            DecodedMap::Regular(ref mut sm) => sm.set_debug_id(debug_id),
+            DecodedMap::Index(ref mut smi) => smi.set_debug_id(debug_id),
+            DecodedMap::Hermes(ref mut smh) => smh.set_debug_id(debug_id),
+        }
+    }
+
+    /// Checks if everything from the given generated line on can be moved.
+    pub(crate) fn can_insert_dst_lines(&self, line: u32) -> bool {
+        match *self {
+            DecodedMap::Index(ref smi) => smi.can_insert_dst_lines(line),
+            DecodedMap::Regular(_) | DecodedMap::Hermes(_) => true,
+        }
+    }
+
+    /// Moves everything from the given generated line on down by `count`
+    /// lines.
+    pub(crate) fn insert_dst_lines(&mut self, line: u32, count: u32) {
+        match *self {
+            DecodedMap::Regular(ref mut sm) => sm.insert_dst_lines(line, count),
+            DecodedMap::Index(ref mut smi) => smi.insert_dst_lines(line, count),
+            DecodedMap::Hermes(ref mut smh) => smh.insert_dst_lines(line, count),
+        }
+    }
+
+    /// Returns the contained sourcemap as a `SourceMapLike` trait object.
+    pub fn as_sourcemap_like(&self) -> &dyn SourceMapLike {
+        match *self {
+            DecodedMap::Regular(ref sm) => sm,
+            DecodedMap::Index(ref smi) => smi,
+            DecodedMap::Hermes(ref smh) => smh,
+        }
+    }
+
+    /// Looks up a token for a line and column with the given options.
+    pub fn lookup_token_with(
+        &self,
+        line: u32,
+        col: u32,
+        options: LookupOptions,
+    ) -> Option<Token<'_>> {
+        match *self {
+            DecodedMap::Regular(ref sm) => sm.lookup_token_with(line, col, options),
+            DecodedMap::Index(ref smi) => smi.lookup_token_with(line, col, options),
+            DecodedMap::Hermes(ref smh) => smh.lookup_token_with(line, col, options),
+        }
+    }
+
+    /// Looks up the closest tokens for many lines and columns at once.
+    pub fn lookup_tokens(&self, positions: &[(u32, u32)]) -> Vec<Option<Token<'_>>> {
+        match *self {
+            DecodedMap::Regular(ref sm) => sm.lookup_tokens(positions),
+            DecodedMap::Index(ref smi) => smi.lookup_tokens(positions),
+            DecodedMap::Hermes(ref smh) => smh.lookup_tokens(positions),
+        }
+    }
+
     /// Returns the original function name.
     ///
     /// `minified_name` and `source_view` are not always necessary.  For
     /// instance hermes source maps can provide this information without
-    /// access to the original sources.
+    /// access to the original sources.  This is the same as
+    /// [`SourceMapLike::original_function_name`], so for hermes source maps
+    /// `line` and `col` are a position in the minified file and not a
+    /// bytecode offset; use `SourceMapHermes::get_original_function_name`
+    /// for those.
     pub fn get_original_function_name(
         &self,
         line: u32,
@@ -105,20 +287,7 @@ impl DecodedMap {
         minified_name: Option<&str>,
         source_view: Option<&SourceView>,
     ) -> Option<&str> {
-        match *self {
-            DecodedMap::Regular(ref sm) => {
-                sm.get_original_function_name(line, col, minified_name?, source_view?)
-            }
-            DecodedMap::Index(ref smi) => {
-                smi.get_original_function_name(line, col, minified_name?, source_view?)
-            }
-            DecodedMap::Hermes(ref smh) => {
-                if line != 0 {
-                    return None;
-                }
-                smh.get_original_function_name(col)
-            }
-        }
+        SourceMapLike::original_function_name(self, line, col, minified_name, source_view)
     }
 }
 
@@ -277,6 +446,143 @@ impl<'a> Token<'a> {
     pub fn get_source_view(&self) -> Option<&SourceView<'_>> {
         self.i.get_source_view(self.get_src_id())
     }
+
+    /// Returns the original source lines around the token.
+    ///
+    /// This requires the source contents of the token's source.  Long lines
+    /// are trimmed around the original column, see
+    /// [`SourceView::get_context_at`](struct.SourceView.html#method.get_context_at).
+    pub fn get_context(&self, before: u32, after: u32) -> Option<SourceContext> {
+        self.get_source_view()?.get_context_at(
+            self.get_src_line(),
+            self.get_src_col(),
+            before,
+            after,
+        )
+    }
+
+    /// Converts the token into an `OwnedToken` that does not borrow the
+    /// sourcemap.
+    ///
+    /// If the sourcemap has the source contents of the token's source the
+    /// original source line is included as context line.
+    pub fn to_owned_token(&self) -> OwnedToken {
+        OwnedToken {
+            dst_line: self.get_dst_line(),
+            dst_col: self.get_dst_col(),
+            src_line: self.get_src_line(),
+            src_col: self.get_src_col(),
+            source: self.get_source().map(str::to_owned),
+            name: self.get_name().map(str::to_owned),
+            context_line: self
+                .get_source_view()
+                .and_then(|sv| sv.get_line(self.get_src_line()))
+                .map(str::to_owned),
+        }
+    }
+}
+
+/// A resolved token that owns its data.
+///
+/// Unlike `Token` this does not borrow the sourcemap it was looked up in so
+/// it can outlive the map, be sent to other threads or, with the `serde`
+/// feature, be serialized.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedToken {
+    dst_line: u32,
+    dst_col: u32,
+    src_line: u32,
+    src_col: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    source: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    context_line: Option<String>,
+}
+
+impl OwnedToken {
+    /// get the destination (minified) line number
+    pub fn get_dst_line(&self) -> u32 {
+        self.dst_line
+    }
+
+    /// get the destination (minified) column number
+    pub fn get_dst_col(&self) -> u32 {
+        self.dst_col
+    }
+
+    /// get the destination line and column
+    pub fn get_dst(&self) -> (u32, u32) {
+        (self.get_dst_line(), self.get_dst_col())
+    }
+
+    /// get the source line number
+    pub fn get_src_line(&self) -> u32 {
+        self.src_line
+    }
+
+    /// get the source column number
+    pub fn get_src_col(&self) -> u32 {
+        self.src_col
+    }
+
+    /// get the source line and column
+    pub fn get_src(&self) -> (u32, u32) {
+        (self.get_src_line(), self.get_src_col())
+    }
+
+    /// get the source if it exists as string
+    pub fn get_source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// get the name if it exists as string
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the original source line if the source contents were known.
+    pub fn get_context_line(&self) -> Option<&str> {
+        self.context_line.as_deref()
+    }
+
+    /// Converts the token into a debug tuple in the form
+    /// `(source, src_line, src_col, name)`
+    pub fn to_tuple(&self) -> (&str, u32, u32, Option<&str>) {
+        (
+            self.get_source().unwrap_or(""),
+            self.get_src_line(),
+            self.get_src_col(),
+            self.get_name(),
+        )
+    }
+}
+
+impl fmt::Display for OwnedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}{}",
+            self.get_source().unwrap_or("<unknown>"),
+            self.get_src_line(),
+            self.get_src_col(),
+            self.get_name()
+                .map(|x| format!(" name={x}"))
+                .unwrap_or_default()
+        )?;
+        if f.alternate() {
+            write!(f, " ({}:{})", self.get_dst_line(), self.get_dst_col())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<Token<'a>> for OwnedToken {
+    fn from(token: Token<'a>) -> OwnedToken {
+        token.to_owned_token()
+    }
 }
 
 pub fn idx_from_token(token: &Token<'_>) -> u32 {
@@ -310,9 +616,8 @@ impl<'a> Iterator for TokenIter<'a> {
     type Item = Token<'a>;
 
     fn next(&mut self) -> Option<Token<'a>> {
-        self.i.get_token(self.next_idx).map(|tok| {
+        self.i.get_token(self.next_idx).inspect(|_| {
             self.next_idx += 1;
-            tok
         })
     }
 }
@@ -327,9 +632,8 @@ impl<'a> Iterator for SourceIter<'a> {
     type Item = &'a str;
 
     fn next(&mut self) -> Option<&'a str> {
-        self.i.get_source(self.next_idx).map(|source| {
+        self.i.get_source(self.next_idx).inspect(|_| {
             self.next_idx += 1;
-            source
         })
     }
 }
@@ -364,9 +668,8 @@ impl<'a> Iterator for NameIter<'a> {
     type Item = &'a str;
 
     fn next(&mut self) -> Option<&'a str> {
-        self.i.get_name(self.next_idx).map(|name| {
+        self.i.get_name(self.next_idx).inspect(|_| {
             self.next_idx += 1;
-            name
         })
     }
 }
@@ -431,9 +734,8 @@ impl<'a> Iterator for SourceMapSectionIter<'a> {
     type Item = &'a SourceMapSection;
 
     fn next(&mut self) -> Option<&'a SourceMapSection> {
-        self.i.get_section(self.next_idx).map(|sec| {
+        self.i.get_section(self.next_idx).inspect(|_| {
             self.next_idx += 1;
-            sec
         })
     }
 }
@@ -445,6 +747,7 @@ pub struct SourceMapIndex {
     sections: Vec<SourceMapSection>,
     x_facebook_offsets: Option<Vec<Option<u32>>>,
     x_metro_module_paths: Option<Vec<String>>,
+    debug_id: Option<DebugId>,
 }
 
 /// Represents a sourcemap in memory
@@ -461,6 +764,7 @@ pub struct SourceMap {
     source_root: Option<String>,
     sources: Vec<String>,
     sources_content: Vec<Option<SourceView<'static>>>,
+    debug_id: Option<DebugId>,
 }
 
 impl SourceMap {
@@ -515,6 +819,47 @@ impl SourceMap {
         encode(self, w)
     }
 
+    /// Writes a sourcemap into a writer with the given options.
+    ///
+    /// ```rust
+    /// # use sourcemap::{EncodeOptions, SourceMap};
+    /// # let input: &[_] = b"{
+    /// #     \"version\":3,
+    /// #     \"sources\":[\"coolstuff.js\"],
+    /// #     \"names\":[\"x\",\"alert\"],
+    /// #     \"mappings\":\"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM\"
+    /// # }";
+    /// let sm = SourceMap::from_reader(input).unwrap();
+    /// let mut output : Vec<u8> = vec![];
+    /// sm.to_writer_with(&mut output, &EncodeOptions {
+    ///     pretty: true,
+    ///     with_names: false,
+    ///     ..Default::default()
+    /// }).unwrap();
+    /// ```
+    pub fn to_writer_with<W: Write>(&self, w: W, options: &EncodeOptions<'_>) -> Result<()> {
+        encode_with(self, w, options)
+    }
+
+    /// Encodes the sourcemap as a base64 data URL.
+    ///
+    /// The result can be used as an inline `sourceMappingURL`:
+    ///
+    /// ```rust
+    /// # use sourcemap::SourceMap;
+    /// # let input: &[_] = b"{
+    /// #     \"version\":3,
+    /// #     \"sources\":[\"coolstuff.js\"],
+    /// #     \"names\":[\"x\",\"alert\"],
+    /// #     \"mappings\":\"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM\"
+    /// # }";
+    /// let sm = SourceMap::from_reader(input).unwrap();
+    /// let comment = format!("//# sourceMappingURL={}", sm.to_data_url().unwrap());
+    /// ```
+    pub fn to_data_url(&self) -> Result<String> {
+        encode_data_url(self)
+    }
+
     /// Creates a sourcemap from a reader over a JSON byte slice in UTF-8
     /// format.  Optionally a "garbage header" as defined by the
     /// sourcemap draft specification is supported.  In case an indexed
@@ -556,7 +901,15 @@ impl SourceMap {
             .enumerate()
             .map(|(idx, token)| (token.dst_line, token.dst_col, idx as u32))
             .collect();
-        index.sort_unstable();
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::slice::ParallelSliceMut;
+            index.par_sort_unstable();
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            index.sort_unstable();
+        }
         SourceMap {
             file,
             tokens,
@@ -569,6 +922,7 @@ impl SourceMap {
                 .into_iter()
                 .map(|opt| opt.map(SourceView::from_string))
                 .collect(),
+            debug_id: None,
         }
     }
 
@@ -592,6 +946,32 @@ impl SourceMap {
         self.source_root = value.map(Into::into);
     }
 
+    /// Returns the debug ID of the sourcemap in case there is one.
+    pub fn get_debug_id(&self) -> Option<DebugId> {
+        self.debug_id
+    }
+
+    /// Sets a new value for the debug ID.
+    pub fn set_debug_id(&mut self, debug_id: Option<DebugId>) {
+        self.debug_id = debug_id;
+    }
+
+    /// Moves all tokens from the given generated line on down by `count`
+    /// lines.
+    pub(crate) fn insert_dst_lines(&mut self, line: u32, count: u32) {
+        for token in &mut self.tokens {
+            if token.dst_line >= line {
+                token.dst_line += count;
+            }
+        }
+        // the order of the index is not affected
+        for entry in &mut self.index {
+            if entry.0 >= line {
+                entry.0 += count;
+            }
+        }
+    }
+
     /// Looks up a token by its index.
     pub fn get_token(&self, idx: u32) -> Option<Token<'_>> {
         self.tokens
@@ -599,6 +979,12 @@ impl SourceMap {
             .map(|raw| Token { raw, i: self, idx })
     }
 
+    /// Returns the raw tokens in their original order.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn raw_tokens(&self) -> &[RawToken] {
+        &self.tokens
+    }
+
     /// Returns the number of tokens in the sourcemap.
     pub fn get_token_count(&self) -> u32 {
         self.tokens.len() as u32
@@ -618,6 +1004,53 @@ impl SourceMap {
         self.get_token(ii.2)
     }
 
+    /// Looks up a token for a 0-indexed line and column with the given
+    /// options.
+    ///
+    /// ```rust
+    /// use sourcemap::{Bias, LookupOptions, SourceMap};
+    /// # let input: &[_] = b"{
+    /// #     \"version\":3,
+    /// #     \"sources\":[\"coolstuff.js\"],
+    /// #     \"names\":[\"x\",\"alert\"],
+    /// #     \"mappings\":\"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM\"
+    /// # }";
+    /// let sm = SourceMap::from_slice(input).unwrap();
+    /// let options = LookupOptions {
+    ///     bias: Bias::LeastUpperBound,
+    ///     ..Default::default()
+    /// };
+    /// let token = sm.lookup_token_with(0, 1, options).unwrap();
+    /// assert_eq!(token.get_dst(), (0, 3));
+    /// ```
+    pub fn lookup_token_with(
+        &self,
+        line: u32,
+        col: u32,
+        options: LookupOptions,
+    ) -> Option<Token<'_>> {
+        let key = (line, col);
+        let ii = match options.bias {
+            Bias::GreatestLowerBound => greatest_lower_bound(&self.index, &key, |ii| (ii.0, ii.1)),
+            Bias::LeastUpperBound => least_upper_bound(&self.index, &key, |ii| (ii.0, ii.1)),
+        }?;
+        if options.same_line_only && ii.0 != line {
+            return None;
+        }
+        self.get_token(ii.2)
+    }
+
+    /// Looks up the closest tokens for many 0-indexed lines and columns.
+    ///
+    /// This returns the same tokens as calling `lookup_token` for every
+    /// position but resolves them together in a single pass over the index.
+    pub fn lookup_tokens(&self, positions: &[(u32, u32)]) -> Vec<Option<Token<'_>>> {
+        greatest_lower_bounds(&self.index, positions, |ii| (ii.0, ii.1))
+            .into_iter()
+            .map(|ii| self.get_token(self.index[ii?].2))
+            .collect()
+    }
+
     /// Given a location, name and minified source file resolve a minified
     /// name to an original function name.
     ///
@@ -805,7 +1238,8 @@ impl SourceMap {
 
         let mapping = builder.take_mapping();
 
-        let sm = builder.into_sourcemap();
+        let mut sm = builder.into_sourcemap();
+        sm.set_debug_id(self.debug_id);
 
         Ok((sm, mapping))
     }
@@ -828,6 +1262,18 @@ impl SourceMapIndex {
         encode(self, w)
     }
 
+    /// Writes a sourcemap index into a writer with the given options.
+    ///
+    /// The options are applied to all embedded sourcemaps.
+    pub fn to_writer_with<W: Write>(&self, w: W, options: &EncodeOptions<'_>) -> Result<()> {
+        encode_with(self, w, options)
+    }
+
+    /// Encodes the sourcemap index as a base64 data URL.
+    pub fn to_data_url(&self) -> Result<String> {
+        encode_data_url(self)
+    }
+
     /// Creates a sourcemap index from a reader over a JSON byte slice in UTF-8
     /// format.  Optionally a "garbage header" as defined by the
     /// sourcemap draft specification is supported.  In case a regular
@@ -849,6 +1295,7 @@ impl SourceMapIndex {
             sections,
             x_facebook_offsets: None,
             x_metro_module_paths: None,
+            debug_id: None,
         }
     }
 
@@ -870,6 +1317,7 @@ impl SourceMapIndex {
             sections,
             x_facebook_offsets,
             x_metro_module_paths,
+            debug_id: None,
         }
     }
 
@@ -883,6 +1331,46 @@ impl SourceMapIndex {
         self.file = value.map(str::to_owned);
     }
 
+    /// Returns the debug ID of the index in case there is one.
+    pub fn get_debug_id(&self) -> Option<DebugId> {
+        self.debug_id
+    }
+
+    /// Sets a new value for the debug ID.
+    pub fn set_debug_id(&mut self, debug_id: Option<DebugId>) {
+        self.debug_id = debug_id;
+    }
+
+    /// Checks if `insert_dst_lines` can move everything from the given
+    /// generated line on.
+    ///
+    /// Sections that start before the line but only reference their
+    /// sourcemap by URL cannot be changed.
+    pub(crate) fn can_insert_dst_lines(&self, line: u32) -> bool {
+        self.sections.iter().all(|section| {
+            section.offset.0 >= line
+                || section
+                    .map
+                    .as_ref()
+                    .is_some_and(|map| map.can_insert_dst_lines(line - section.offset.0))
+        })
+    }
+
+    /// Moves everything from the given generated line on down by `count`
+    /// lines.
+    ///
+    /// Sections without an embedded sourcemap are skipped, check with
+    /// `can_insert_dst_lines` first.
+    pub(crate) fn insert_dst_lines(&mut self, line: u32, count: u32) {
+        for section in &mut self.sections {
+            if section.offset.0 >= line {
+                section.offset.0 += count;
+            } else if let Some(ref mut map) = section.map {
+                map.insert_dst_lines(line - section.offset.0, count);
+            }
+        }
+    }
+
     /// Returns the number of sections in this index
     pub fn get_section_count(&self) -> u32 {
         self.sections.len() as u32
@@ -925,14 +1413,34 @@ impl SourceMapIndex {
             .and_then(|token| sv.get_original_function_name(token, minified_name))
     }
 
+    /// Returns the index of the section a position falls into.
+    ///
+    /// Of several sections with the same offset the first one is picked if
+    /// the position is at that offset, just like `greatest_lower_bound` and
+    /// `lookup_tokens` do.
+    fn find_section(&self, line: u32, col: u32) -> Option<usize> {
+        let key = (line, col);
+        let idx = self
+            .sections
+            .partition_point(|section| section.get_offset() < key);
+        if self
+            .sections
+            .get(idx)
+            .is_some_and(|section| section.get_offset() == key)
+        {
+            Some(idx)
+        } else {
+            idx.checked_sub(1)
+        }
+    }
+
     /// Looks up the closest token to a given line and column.
     ///
     /// This requires that the referenced sourcemaps are actually loaded.
     /// If a sourcemap is encountered that is not embedded but just
     /// externally referenced it is silently skipped.
     pub fn lookup_token(&self, line: u32, col: u32) -> Option<Token<'_>> {
-        let section =
-            greatest_lower_bound(&self.sections, &(line, col), SourceMapSection::get_offset)?;
+        let section = &self.sections[self.find_section(line, col)?];
         let map = section.get_sourcemap()?;
         let (off_line, off_col) = section.get_offset();
         map.lookup_token(
@@ -941,9 +1449,113 @@ impl SourceMapIndex {
         )
     }
 
+    /// Looks up a token for a line and column with the given options.
+    ///
+    /// Lookups with a greatest lower bound bias stay within the section the
+    /// position falls into, just like `lookup_token`.  Lookups with a least
+    /// upper bound bias continue with the following sections if there is
+    /// no later token in that section.
+    pub fn lookup_token_with(
+        &self,
+        line: u32,
+        col: u32,
+        options: LookupOptions,
+    ) -> Option<Token<'_>> {
+        let idx = self.find_section(line, col);
+
+        if options.bias == Bias::GreatestLowerBound {
+            let section = &self.sections[idx?];
+            let (off_line, off_col) = section.get_offset();
+            return section.get_sourcemap()?.lookup_token_with(
+                line - off_line,
+                if line == off_line { col - off_col } else { col },
+                options,
+            );
+        }
+
+        if let Some(section) = idx.map(|idx| &self.sections[idx]) {
+            let (off_line, off_col) = section.get_offset();
+            let token = section.get_sourcemap().and_then(|map| {
+                map.lookup_token_with(
+                    line - off_line,
+                    if line == off_line { col - off_col } else { col },
+                    options,
+                )
+            });
+            if token.is_some() {
+                return token;
+            }
+        }
+
+        for section in &self.sections[idx.map_or(0, |idx| idx + 1)..] {
+            if options.same_line_only && section.get_offset_line() != line {
+                break;
+            }
+            let token = section
+                .get_sourcemap()
+                .and_then(|map| map.lookup_token_with(0, 0, options));
+            if token.is_some() {
+                return token;
+            }
+        }
+
+        None
+    }
+
+    /// Looks up the closest tokens for many lines and columns.
+    ///
+    /// The positions are grouped by section so that every embedded
+    /// sourcemap is only searched once.
+    pub fn lookup_tokens(&self, positions: &[(u32, u32)]) -> Vec<Option<Token<'_>>> {
+        let sections =
+            greatest_lower_bounds(&self.sections, positions, SourceMapSection::get_offset);
+
+        let mut indexes = vec![vec![]; self.sections.len()];
+        let mut section_positions = vec![vec![]; self.sections.len()];
+        for (idx, (&(line, col), section_idx)) in positions.iter().zip(sections).enumerate() {
+            if let Some(section_idx) = section_idx {
+                let (off_line, off_col) = self.sections[section_idx].get_offset();
+                indexes[section_idx].push(idx);
+                section_positions[section_idx].push((
+                    line - off_line,
+                    if line == off_line { col - off_col } else { col },
+                ));
+            }
+        }
+
+        let mut rv = vec![None; positions.len()];
+        for (section_idx, section) in self.sections.iter().enumerate() {
+            let map = match section.get_sourcemap() {
+                Some(map) if !indexes[section_idx].is_empty() => map,
+                _ => continue,
+            };
+            let tokens = map.lookup_tokens(&section_positions[section_idx]);
+            for (&idx, token) in indexes[section_idx].iter().zip(tokens) {
+                rv[idx] = token;
+            }
+        }
+        rv
+    }
+
     /// Flattens an indexed sourcemap into a regular one.  This requires
     /// that all referenced sourcemaps are attached.
+    ///
+    /// With the `rayon` feature enabled the sections of large indexes are
+    /// processed on multiple threads.
     pub fn flatten(&self) -> Result<SourceMap> {
+        #[cfg(feature = "rayon")]
+        {
+            if rayon::current_num_threads() > 1
+                && crate::parallel::index_token_count(self)
+                    >= crate::parallel::PARALLEL_FLATTEN_THRESHOLD
+            {
+                return crate::parallel::flatten(self);
+            }
+        }
+        self.flatten_serial()
+    }
+
+    pub(crate) fn flatten_serial(&self) -> Result<SourceMap> {
         let mut builder = SourceMapBuilder::new(self.get_file());
 
         for section in self.sections() {
@@ -1065,3 +1677,115 @@ impl SourceMapSection {
         self.map = sm.map(Box::new);
     }
 }
+
+impl SourceMapLike for SourceMap {
+    fn lookup_token(&self, line: u32, col: u32) -> Option<Token<'_>> {
+        SourceMap::lookup_token(self, line, col)
+    }
+
+    fn tokens(&self) -> Box<dyn Iterator<Item = Token<'_>> + '_> {
+        Box::new(SourceMap::tokens(self))
+    }
+
+    fn sources(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        Box::new(SourceMap::sources(self))
+    }
+
+    fn source_contents(&self) -> Box<dyn Iterator<Item = Option<&str>> + '_> {
+        Box::new(SourceMap::source_contents(self))
+    }
+
+    fn original_function_name(
+        &self,
+        line: u32,
+        col: u32,
+        minified_name: Option<&str>,
+        source_view: Option<&SourceView<'_>>,
+    ) -> Option<&str> {
+        let token = SourceMap::lookup_token(self, line, col)?;
+        source_view?.get_original_function_name(token, minified_name?)
+    }
+}
+
+impl SourceMapLike for SourceMapIndex {
+    fn lookup_token(&self, line: u32, col: u32) -> Option<Token<'_>> {
+        SourceMapIndex::lookup_token(self, line, col)
+    }
+
+    fn tokens(&self) -> Box<dyn Iterator<Item = Token<'_>> + '_> {
+        Box::new(
+            self.sections
+                .iter()
+                .filter_map(|section| section.get_sourcemap())
+                .flat_map(|map| map.tokens()),
+        )
+    }
+
+    fn sources(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        Box::new(
+            self.sections
+                .iter()
+                .filter_map(|section| section.get_sourcemap())
+                .flat_map(|map| map.sources()),
+        )
+    }
+
+    fn source_contents(&self) -> Box<dyn Iterator<Item = Option<&str>> + '_> {
+        Box::new(
+            self.sections
+                .iter()
+                .filter_map(|section| section.get_sourcemap())
+                .flat_map(|map| map.source_contents()),
+        )
+    }
+
+    fn original_function_name(
+        &self,
+        line: u32,
+        col: u32,
+        minified_name: Option<&str>,
+        source_view: Option<&SourceView<'_>>,
+    ) -> Option<&str> {
+        let token = SourceMapIndex::lookup_token(self, line, col)?;
+        source_view?.get_original_function_name(token, minified_name?)
+    }
+}
+
+impl SourceMapLike for DecodedMap {
+    fn lookup_token(&self, line: u32, col: u32) -> Option<Token<'_>> {
+        DecodedMap::lookup_token(self, line, col)
+    }
+
+    fn tokens(&self) -> Box<dyn Iterator<Item = Token<'_>> + '_> {
+        self.as_sourcemap_like().tokens()
+    }
+
+    fn sources(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        self.as_sourcemap_like().sources()
+    }
+
+    fn source_contents(&self) -> Box<dyn Iterator<Item = Option<&str>> + '_> {
+        self.as_sourcemap_like().source_contents()
+    }
+
+    fn original_function_name(
+        &self,
+        line: u32,
+        col: u32,
+        minified_name: Option<&str>,
+        source_view: Option<&SourceView<'_>>,
+    ) -> Option<&str> {
+        self.as_sourcemap_like()
+            .original_function_name(line, col, minified_name, source_view)
+    }
+}
+
+// All sourcemap types can be shared between threads.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    let _ = assert_send_sync::<SourceView<'static>>;
+    let _ = assert_send_sync::<SourceMap>;
+    let _ = assert_send_sync::<SourceMapIndex>;
+    let _ = assert_send_sync::<SourceMapHermes>;
+    let _ = assert_send_sync::<DecodedMap>;
+};
@@ -1,5 +1,5 @@
 use std::borrow::Cow;
-use std::iter::repeat;
+use std::iter::repeat_n;
 
 fn split_path(path: &str) -> Vec<&str> {
     let mut last_idx = 0;
@@ -105,7 +105,7 @@ pub fn make_relative_path(base: &str, target: &str) -> String {
     let prefix = find_common_prefix_of_sorted_vec(&items)
         .map(|x| x.len())
         .unwrap_or(0);
-    let mut rel_list: Vec<_> = repeat("../").take(base_path.len() - prefix).collect();
+    let mut rel_list: Vec<_> = repeat_n("../", base_path.len() - prefix).collect();
     rel_list.extend_from_slice(&target_path[prefix..]);
     if rel_list.is_empty() {
         ".".into()
@@ -141,6 +141,42 @@ pub fn greatest_lower_bound<'a, T, K: Ord, F: Fn(&'a T) -> K>(
     slice.get(idx)
 }
 
+/// Returns the first element whose key is not smaller than the given key.
+pub fn least_upper_bound<'a, T, K: Ord, F: Fn(&T) -> K>(
+    slice: &'a [T],
+    key: &K,
+    map: F,
+) -> Option<&'a T> {
+    slice.get(slice.partition_point(|item| map(item) < *key))
+}
+
+/// Resolves the greatest lower bounds of many keys at once.
+///
+/// Returns the index into `slice` for every key, in the order of the keys.
+/// The keys are sorted first so that every search only has to look at the
+/// part of the slice after the previous result.
+pub fn greatest_lower_bounds<T, K: Ord, F: Fn(&T) -> K>(
+    slice: &[T],
+    keys: &[K],
+    map: F,
+) -> Vec<Option<usize>> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+    let mut rv = vec![None; keys.len()];
+    let mut pos = 0;
+    for idx in order {
+        let key = &keys[idx];
+        pos += slice[pos..].partition_point(|item| map(item) < *key);
+        rv[idx] = if slice.get(pos).is_some_and(|item| map(item) == *key) {
+            Some(pos)
+        } else {
+            pos.checked_sub(1)
+        };
+    }
+    rv
+}
+
 #[test]
 fn test_is_abs_path() {
     assert!(is_abs_path("C:\\foo.txt"));
@@ -225,3 +261,36 @@ fn test_greatest_lower_bound() {
     assert_eq!(greatest_lower_bound(&haystack, &2, cmp), Some(&(1, 5)));
     assert_eq!(greatest_lower_bound(&haystack, &0, cmp), None);
 }
+
+#[test]
+fn test_least_upper_bound() {
+    fn cmp(&(i, _id): &(i32, i32)) -> i32 {
+        i
+    }
+
+    let haystack = vec![(1, 1), (3, 2), (3, 3), (5, 4)];
+    assert_eq!(least_upper_bound(&haystack, &0, cmp), Some(&(1, 1)));
+    assert_eq!(least_upper_bound(&haystack, &1, cmp), Some(&(1, 1)));
+    assert_eq!(least_upper_bound(&haystack, &2, cmp), Some(&(3, 2)));
+    assert_eq!(least_upper_bound(&haystack, &3, cmp), Some(&(3, 2)));
+    assert_eq!(least_upper_bound(&haystack, &5, cmp), Some(&(5, 4)));
+    assert_eq!(least_upper_bound(&haystack, &6, cmp), None);
+}
+
+#[test]
+fn test_greatest_lower_bounds() {
+    fn cmp(&(i, _id): &(i32, i32)) -> i32 {
+        i
+    }
+    let haystack = vec![(1, 1), (1, 2), (3, 3), (5, 4), (5, 5)];
+    let keys = [5, 0, 1, 2, 7, 3, 4];
+    let expected: Vec<_> = keys
+        .iter()
+        .map(|key| greatest_lower_bound(&haystack, key, cmp))
+        .collect();
+    let found: Vec<_> = greatest_lower_bounds(&haystack, &keys, cmp)
+        .into_iter()
+        .map(|idx| idx.map(|idx| &haystack[idx]))
+        .collect();
+    assert_eq!(found, expected);
+}
@@ -1,5 +1,8 @@
 //! Implements utilities for dealing with the sourcemap vlq encoding.
+use std::io;
+
 use crate::errors::{Error, Result};
+use crate::types::RawToken;
 
 const B64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 const B64: [i8; 256] = [
@@ -328,6 +331,312 @@ pub(crate) fn encode_vlq(out: &mut String, num: i64) {
     }
 }
 
+/// The absolute state of the mappings state machine at the start of a line.
+#[cfg(feature = "rayon")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MappingsState {
+    pub dst_line: u32,
+    pub src_id: u32,
+    pub src_line: u32,
+    pub src_col: u32,
+    pub name_id: u32,
+}
+
+#[cfg(feature = "rayon")]
+impl MappingsState {
+    /// Applies the state changes of a line range that was decoded from a
+    /// zeroed state.
+    pub fn advance(self, delta: MappingsState) -> MappingsState {
+        MappingsState {
+            dst_line: self.dst_line.wrapping_add(delta.dst_line),
+            src_id: self.src_id.wrapping_add(delta.src_id),
+            src_line: self.src_line.wrapping_add(delta.src_line),
+            src_col: self.src_col.wrapping_add(delta.src_col),
+            name_id: self.name_id.wrapping_add(delta.name_id),
+        }
+    }
+}
+
+/// An iterator that decodes the `mappings` string of a sourcemap.
+///
+/// This runs the full mappings state machine (relative source, line, column
+/// and name deltas across `;` and `,`) and yields a `RawToken` per segment
+/// without building a `SourceMap`.  Tokens without a source have a `src_id`
+/// of `!0`, tokens without a name a `name_id` of `!0`.
+///
+/// After the first error the iterator is exhausted.
+///
+/// ```
+/// use sourcemap::vlq::MappingsDecoder;
+/// let tokens: Vec<_> = MappingsDecoder::new("AAAA,GAAIA;CACA")
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(tokens.len(), 3);
+/// assert_eq!((tokens[1].dst_col, tokens[1].src_col, tokens[1].name_id), (3, 4, 0));
+/// assert_eq!((tokens[2].dst_line, tokens[2].src_line), (1, 1));
+/// ```
+pub struct MappingsDecoder<'a> {
+    mappings: &'a [u8],
+    pos: usize,
+    dst_line: u32,
+    dst_col: u32,
+    src_id: u32,
+    src_line: u32,
+    src_col: u32,
+    name_id: u32,
+    limits: Option<(u32, u32)>,
+    failed: bool,
+}
+
+impl<'a> MappingsDecoder<'a> {
+    /// Creates a decoder for the given mappings string.
+    pub fn new(mappings: &'a str) -> MappingsDecoder<'a> {
+        MappingsDecoder {
+            mappings: mappings.as_bytes(),
+            pos: 0,
+            dst_line: 0,
+            dst_col: 0,
+            src_id: 0,
+            src_line: 0,
+            src_col: 0,
+            name_id: 0,
+            limits: None,
+            failed: false,
+        }
+    }
+
+    /// Makes the decoder validate source and name references.
+    ///
+    /// References to sources or names at or past the given counts fail with
+    /// `BadSourceReference` or `BadNameReference`.
+    pub fn check_references(mut self, source_count: u32, name_count: u32) -> Self {
+        self.limits = Some((source_count, name_count));
+        self
+    }
+
+    /// Starts decoding with the given state.
+    ///
+    /// The mappings must start at the beginning of a line.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn with_state(mut self, state: MappingsState) -> Self {
+        self.dst_line = state.dst_line;
+        self.src_id = state.src_id;
+        self.src_line = state.src_line;
+        self.src_col = state.src_col;
+        self.name_id = state.name_id;
+        self
+    }
+
+    /// Returns the current state of the decoder.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn state(&self) -> MappingsState {
+        MappingsState {
+            dst_line: self.dst_line,
+            src_id: self.src_id,
+            src_line: self.src_line,
+            src_col: self.src_col,
+            name_id: self.name_id,
+        }
+    }
+
+    /// Decodes the VLQ values of the segment starting at the current position.
+    ///
+    /// This works directly on the mappings bytes and stops at the next `,` or
+    /// `;`.  Only the first five values are stored but all of them are
+    /// counted so that oversized segments can be reported.  Errors match the
+    /// ones of `parse_vlq_segment`.
+    #[inline]
+    fn decode_values(&mut self, values: &mut [i64; 5]) -> Result<usize> {
+        let mut count = 0;
+        let mut cur: i64 = 0;
+        let mut shift = 0;
+
+        while let Some(&c) = self.mappings.get(self.pos) {
+            if c == b',' || c == b';' {
+                break;
+            }
+            self.pos += 1;
+
+            let enc = i64::from(B64[c as usize]);
+            cur += (enc & 0b11111)
+                .checked_shl(shift)
+                .ok_or(Error::VlqOverflow)?;
+            shift += 5;
+
+            if enc >> 5 == 0 {
+                let sign = cur & 1;
+                cur >>= 1;
+                if sign != 0 {
+                    cur = -cur;
+                }
+                if let Some(slot) = values.get_mut(count) {
+                    *slot = cur;
+                }
+                count += 1;
+                cur = 0;
+                shift = 0;
+            }
+        }
+
+        if cur != 0 || shift != 0 {
+            Err(Error::VlqLeftover)
+        } else {
+            Ok(count)
+        }
+    }
+
+    fn decode_segment(&mut self) -> Result<RawToken> {
+        let mut nums = [0; 5];
+        let len = self.decode_values(&mut nums)?;
+        self.dst_col = (i64::from(self.dst_col) + nums[0]) as u32;
+
+        let mut src = !0;
+        let mut name = !0;
+
+        if len > 1 {
+            if len != 4 && len != 5 {
+                fail!(Error::BadSegmentSize(len as u32));
+            }
+            self.src_id = (i64::from(self.src_id) + nums[1]) as u32;
+            if let Some((source_count, _)) = self.limits {
+                if self.src_id >= source_count {
+                    fail!(Error::BadSourceReference(self.src_id));
+                }
+            }
+
+            src = self.src_id;
+            self.src_line = (i64::from(self.src_line) + nums[2]) as u32;
+            self.src_col = (i64::from(self.src_col) + nums[3]) as u32;
+
+            if len > 4 {
+                self.name_id = (i64::from(self.name_id) + nums[4]) as u32;
+                if let Some((_, name_count)) = self.limits {
+                    if self.name_id >= name_count {
+                        fail!(Error::BadNameReference(self.name_id));
+                    }
+                }
+                name = self.name_id;
+            }
+        }
+
+        Ok(RawToken {
+            dst_line: self.dst_line,
+            dst_col: self.dst_col,
+            src_line: self.src_line,
+            src_col: self.src_col,
+            src_id: src,
+            name_id: name,
+        })
+    }
+}
+
+impl<'a> Iterator for MappingsDecoder<'a> {
+    type Item = Result<RawToken>;
+
+    fn next(&mut self) -> Option<Result<RawToken>> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            match *self.mappings.get(self.pos)? {
+                b';' => {
+                    self.pos += 1;
+                    self.dst_line += 1;
+                    self.dst_col = 0;
+                }
+                b',' => {
+                    self.pos += 1;
+                }
+                _ => {
+                    let rv = self.decode_segment();
+                    self.failed = rv.is_err();
+                    return Some(rv);
+                }
+            }
+        }
+    }
+}
+
+/// Encodes `RawToken`s into the `mappings` string of a sourcemap.
+///
+/// This is the counterpart to `MappingsDecoder`.  Tokens must be passed
+/// sorted by their destination position.  Tokens with a `src_id` of `!0`
+/// are encoded without source and tokens with a `name_id` of `!0` without
+/// name.
+///
+/// ```
+/// use sourcemap::vlq::{MappingsDecoder, MappingsEncoder};
+/// let mut encoder = MappingsEncoder::new();
+/// let mut out = String::new();
+/// for token in MappingsDecoder::new("AAAA,GAAIA;CACA") {
+///     encoder.encode(&mut out, &token.unwrap());
+/// }
+/// assert_eq!(out, "AAAA,GAAIA;CACA");
+/// ```
+#[derive(Debug, Default)]
+pub struct MappingsEncoder {
+    dst_line: u32,
+    dst_col: u32,
+    src_id: u32,
+    src_line: u32,
+    src_col: u32,
+    name_id: u32,
+    line_has_segments: bool,
+    buf: String,
+}
+
+impl MappingsEncoder {
+    /// Creates a new encoder.
+    pub fn new() -> MappingsEncoder {
+        MappingsEncoder::default()
+    }
+
+    /// Appends the segment for a token to the given string.
+    pub fn encode(&mut self, out: &mut String, token: &RawToken) {
+        if token.dst_line > self.dst_line {
+            self.dst_col = 0;
+            while token.dst_line > self.dst_line {
+                out.push(';');
+                self.dst_line += 1;
+            }
+        } else if self.line_has_segments {
+            out.push(',');
+        }
+        self.line_has_segments = true;
+
+        encode_vlq_diff(out, token.dst_col, self.dst_col);
+        self.dst_col = token.dst_col;
+
+        if token.src_id != !0 {
+            encode_vlq_diff(out, token.src_id, self.src_id);
+            self.src_id = token.src_id;
+            encode_vlq_diff(out, token.src_line, self.src_line);
+            self.src_line = token.src_line;
+            encode_vlq_diff(out, token.src_col, self.src_col);
+            self.src_col = token.src_col;
+            if token.name_id != !0 {
+                encode_vlq_diff(out, token.name_id, self.name_id);
+                self.name_id = token.name_id;
+            }
+        }
+    }
+
+    /// Writes the segment for a token into the given writer.
+    pub fn write<W: io::Write>(&mut self, mut w: W, token: &RawToken) -> io::Result<()> {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        self.encode(&mut buf, token);
+        let rv = w.write_all(buf.as_bytes());
+        self.buf = buf;
+        rv
+    }
+}
+
+fn encode_vlq_diff(out: &mut String, a: u32, b: u32) {
+    encode_vlq(out, i64::from(a) - i64::from(b))
+}
+
 #[test]
 fn test_vlq_decode() {
     let rv = parse_vlq_segment("AAAA").unwrap();
@@ -353,3 +662,106 @@ fn test_overflow() {
         }
     }
 }
+
+#[test]
+fn test_mappings_roundtrip() {
+    let mappings = "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM;;ACAA,CAAC";
+    let tokens = MappingsDecoder::new(mappings)
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(tokens.len(), 11);
+    assert_eq!(tokens[9].dst_line, 2);
+
+    let mut encoder = MappingsEncoder::new();
+    let mut out = vec![];
+    for token in &tokens {
+        encoder.write(&mut out, token).unwrap();
+    }
+    assert_eq!(std::str::from_utf8(&out).unwrap(), mappings);
+}
+
+#[test]
+fn test_mappings_decoder_errors() {
+    let mut iter = MappingsDecoder::new("AAAA,AA;AAAA");
+    assert!(iter.next().unwrap().is_ok());
+    match iter.next() {
+        Some(Err(Error::BadSegmentSize(2))) => {}
+        e => {
+            panic!("Unexpeted result: {:?}", e);
+        }
+    }
+    assert!(iter.next().is_none());
+
+    let mut iter = MappingsDecoder::new("AAAA,ACAA").check_references(1, 0);
+    assert!(iter.next().unwrap().is_ok());
+    match iter.next() {
+        Some(Err(Error::BadSourceReference(1))) => {}
+        e => {
+            panic!("Unexpeted result: {:?}", e);
+        }
+    }
+}
+
+#[test]
+fn test_mappings_decoder_matches_segment_parser() {
+    // reference implementation on top of the per-segment parser
+    fn reference(mappings: &str) -> Vec<Result<RawToken>> {
+        let mut rv = vec![];
+        let (mut src_id, mut src_line, mut src_col, mut name_id) = (0u32, 0u32, 0u32, 0u32);
+        for (dst_line, line) in mappings.split(';').enumerate() {
+            let mut dst_col = 0u32;
+            for segment in line.split(',').filter(|s| !s.is_empty()) {
+                let nums = match parse_vlq_segment(segment) {
+                    Ok(nums) => nums,
+                    Err(err) => {
+                        rv.push(Err(err));
+                        return rv;
+                    }
+                };
+                dst_col = (i64::from(dst_col) + nums[0]) as u32;
+                let (mut src, mut name) = (!0, !0);
+                if nums.len() > 1 {
+                    if nums.len() != 4 && nums.len() != 5 {
+                        rv.push(Err(Error::BadSegmentSize(nums.len() as u32)));
+                        return rv;
+                    }
+                    src_id = (i64::from(src_id) + nums[1]) as u32;
+                    src = src_id;
+                    src_line = (i64::from(src_line) + nums[2]) as u32;
+                    src_col = (i64::from(src_col) + nums[3]) as u32;
+                    if nums.len() > 4 {
+                        name_id = (i64::from(name_id) + nums[4]) as u32;
+                        name = name_id;
+                    }
+                }
+                rv.push(Ok(RawToken {
+                    dst_line: dst_line as u32,
+                    dst_col,
+                    src_line,
+                    src_col,
+                    src_id: src,
+                    name_id: name,
+                }));
+            }
+        }
+        rv
+    }
+
+    let alphabet = b"AACDEFgkz/+09,;;,,.";
+    let mut seed = 0x2545_f491_u64;
+    for _ in 0..2000 {
+        let mut mappings = String::new();
+        seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        for _ in 0..(seed >> 59) * 4 {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            mappings.push(alphabet[(seed >> 33) as usize % alphabet.len()] as char);
+        }
+        let expected = reference(&mappings);
+        let actual: Vec<_> = MappingsDecoder::new(&mappings).collect();
+        assert_eq!(
+            format!("{actual:?}"),
+            format!("{expected:?}"),
+            "mappings: {mappings:?}"
+        );
+    }
+}
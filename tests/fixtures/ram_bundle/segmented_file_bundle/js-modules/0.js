@@ -0,0 +1 @@
+__d(function(g,r,i,a,m,e,d){r(d[0]);global.__loadSegment(1)},0,[1]);
\ No newline at end of file
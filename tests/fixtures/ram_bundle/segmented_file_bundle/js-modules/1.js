@@ -0,0 +1 @@
+__d(function(g,r,i,a,m,e,d){m.exports=function(n){return n+1}},1,[]);
\ No newline at end of file
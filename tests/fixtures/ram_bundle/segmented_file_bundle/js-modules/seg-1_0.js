@@ -0,0 +1 @@
+__d(function(g,r,i,a,m,e,d){console.log(r(d[0])(41))},0,[2]);
\ No newline at end of file
@@ -0,0 +1 @@
+__d(function(g,r,i,a,m,e,d){m.exports=function(n){return n*2}},2,[]);
\ No newline at end of file
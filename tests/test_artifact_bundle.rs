@@ -0,0 +1,216 @@
+#![cfg(feature = "artifact_bundle")]
+use std::fs;
+use std::io::Cursor;
+
+use sourcemap::artifact_bundle::{ArtifactBundle, ArtifactBundleWriter, ArtifactType};
+use sourcemap::discovery::discover;
+use sourcemap::{DebugId, Error};
+
+const MAP: &str = r#"{"version":3,"sources":["coolstuff.js"],"names":["x","alert"],"mappings":"AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"}"#;
+
+#[test]
+fn test_roundtrip() {
+    let debug_id: DebugId = "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab".parse().unwrap();
+    let mut writer = ArtifactBundleWriter::new(Cursor::new(vec![]));
+    writer
+        .add_file(
+            "~/static/app.js",
+            ArtifactType::MinifiedSource,
+            b"var x = 1;\nalert(x);",
+            Some(debug_id),
+            Some("~/static/app.js.map"),
+        )
+        .unwrap();
+    writer
+        .add_file(
+            "~/static/app.js.map",
+            ArtifactType::SourceMap,
+            MAP.as_bytes(),
+            Some(debug_id),
+            None,
+        )
+        .unwrap();
+    let buf = writer.finish().unwrap().into_inner();
+
+    let bundle = ArtifactBundle::new(Cursor::new(buf)).unwrap();
+    assert_eq!(bundle.entries().len(), 2);
+
+    let entry = bundle
+        .get_entry_by_url("https://example.com/static/app.js")
+        .unwrap();
+    assert_eq!(entry.get_url(), "~/static/app.js");
+    assert_eq!(entry.get_type(), ArtifactType::MinifiedSource);
+    assert_eq!(entry.get_sourcemap_url(), Some("~/static/app.js.map"));
+
+    let sv = bundle
+        .get_source_view_by_url("https://example.com/static/app.js")
+        .unwrap()
+        .unwrap();
+    assert_eq!(sv.get_line(1), Some("alert(x);"));
+
+    let sm = bundle
+        .get_sourcemap_by_url("https://example.com/static/app.js")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        sm.lookup_token(0, 0).unwrap().get_source(),
+        Some("coolstuff.js")
+    );
+
+    let sm = bundle.get_sourcemap_by_debug_id(debug_id).unwrap().unwrap();
+    assert_eq!(sm.lookup_token(0, 4).unwrap().get_name(), Some("x"));
+    assert!(bundle
+        .get_source_view_by_debug_id(debug_id)
+        .unwrap()
+        .is_some());
+
+    assert!(bundle
+        .get_sourcemap_by_url("~/missing.js")
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_deterministic() {
+    let build = || {
+        let mut writer = ArtifactBundleWriter::new(Cursor::new(vec![]));
+        writer
+            .add_file("~/a.js", ArtifactType::MinifiedSource, b"a();", None, None)
+            .unwrap();
+        writer.finish().unwrap().into_inner()
+    };
+    assert_eq!(build(), build());
+}
+
+#[test]
+fn test_from_discovery() {
+    let root = std::env::temp_dir().join(format!("sourcemap-bundle-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("js")).unwrap();
+    fs::write(
+        root.join("js/app.js"),
+        "var x = 1;\nalert(x);\n//# sourceMappingURL=app.js.map",
+    )
+    .unwrap();
+    fs::write(root.join("js/app.js.map"), MAP).unwrap();
+    fs::write(root.join("other.js"), "other();").unwrap();
+
+    let discovery = discover(&root).unwrap();
+    let mut writer = ArtifactBundleWriter::new(Cursor::new(vec![]));
+    writer.add_discovery(&discovery, &root, "~/").unwrap();
+    let buf = writer.finish().unwrap().into_inner();
+
+    let bundle = ArtifactBundle::new(Cursor::new(buf)).unwrap();
+    let mut urls: Vec<_> = bundle.entries().iter().map(|e| e.get_url()).collect();
+    urls.sort_unstable();
+    assert_eq!(urls, vec!["~/js/app.js", "~/js/app.js.map"]);
+
+    let sm = bundle
+        .get_sourcemap_by_url("https://example.com/js/app.js")
+        .unwrap()
+        .unwrap();
+    assert_eq!(sm.lookup_token(0, 4).unwrap().get_name(), Some("x"));
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_duplicate_urls() {
+    let mut writer = ArtifactBundleWriter::new(Cursor::new(vec![]));
+    writer
+        .add_file("~/a.js", ArtifactType::MinifiedSource, b"a();", None, None)
+        .unwrap();
+    writer
+        .add_file("~/a.js", ArtifactType::MinifiedSource, b"a();", None, None)
+        .unwrap();
+    assert!(matches!(
+        writer.add_file("~/a.js", ArtifactType::MinifiedSource, b"b();", None, None),
+        Err(Error::ConflictingArtifact(ref url)) if url == "~/a.js"
+    ));
+    assert!(matches!(
+        writer.add_file("~/a.js", ArtifactType::Source, b"a();", None, None),
+        Err(Error::ConflictingArtifact(_))
+    ));
+    let buf = writer.finish().unwrap().into_inner();
+
+    let bundle = ArtifactBundle::new(Cursor::new(buf)).unwrap();
+    assert_eq!(bundle.entries().len(), 1);
+    let sv = bundle.get_source_view_by_url("~/a.js").unwrap().unwrap();
+    assert_eq!(sv.source(), "a();");
+}
+
+#[test]
+fn test_from_discovery_maps_outside_root() {
+    let base = std::env::temp_dir().join(format!("sourcemap-bundle-out-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&base);
+    let root = base.join("root");
+    for dir in ["root", "maps/a", "maps/b"] {
+        fs::create_dir_all(base.join(dir)).unwrap();
+    }
+    fs::write(
+        root.join("a.js"),
+        "var x = 1;\n//# sourceMappingURL=../maps/a/app.js.map",
+    )
+    .unwrap();
+    fs::write(
+        root.join("b.js"),
+        "alert(1);\n//# sourceMappingURL=../maps/b/app.js.map",
+    )
+    .unwrap();
+    fs::write(base.join("maps/a/app.js.map"), MAP).unwrap();
+    fs::write(
+        base.join("maps/b/app.js.map"),
+        MAP.replace("coolstuff.js", "other.js"),
+    )
+    .unwrap();
+
+    let discovery = discover(&root).unwrap();
+    let mut writer = ArtifactBundleWriter::new(Cursor::new(vec![]));
+    writer.add_discovery(&discovery, &root, "~/").unwrap();
+    let buf = writer.finish().unwrap().into_inner();
+
+    let bundle = ArtifactBundle::new(Cursor::new(buf)).unwrap();
+    assert_eq!(bundle.entries().len(), 4);
+    for (url, source) in [("~/a.js", "coolstuff.js"), ("~/b.js", "other.js")] {
+        let sm = bundle.get_sourcemap_by_url(url).unwrap().unwrap();
+        assert_eq!(sm.lookup_token(0, 0).unwrap().get_source(), Some(source));
+    }
+
+    fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn test_from_discovery_embedded() {
+    let root =
+        std::env::temp_dir().join(format!("sourcemap-bundle-embedded-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(
+        root.join("app.js"),
+        "var x = 1;\nalert(x);\n//# debugId=b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab\n\
+         //# sourceMappingURL=data:application/json;base64,eyJ2ZXJzaW9uIjozLCJzb3VyY2VzIjpbImNvb2xzdHVmZi5qcyJdLCJuYW1lcyI6WyJ4IiwiYWxlcnQiXSwibWFwcGluZ3MiOiJBQUFBLEdBQUlBLEdBQUksRUFDUixJQUFJQSxHQUFLLEVBQUcsQ0FDVkMsTUFBTSJ9",
+    )
+    .unwrap();
+
+    let discovery = discover(&root).unwrap();
+    let mut writer = ArtifactBundleWriter::new(Cursor::new(vec![]));
+    writer.add_discovery(&discovery, &root, "~/").unwrap();
+    let buf = writer.finish().unwrap().into_inner();
+
+    let bundle = ArtifactBundle::new(Cursor::new(buf)).unwrap();
+    assert_eq!(bundle.entries().len(), 1);
+    let entry = bundle.get_entry_by_url("~/app.js").unwrap();
+    assert_eq!(entry.get_sourcemap_url(), None);
+
+    let sm = bundle.get_sourcemap_by_url("~/app.js").unwrap().unwrap();
+    assert_eq!(sm.lookup_token(0, 4).unwrap().get_name(), Some("x"));
+
+    let debug_id: DebugId = "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab".parse().unwrap();
+    let sm = bundle.get_sourcemap_by_debug_id(debug_id).unwrap().unwrap();
+    assert_eq!(
+        sm.lookup_token(0, 0).unwrap().get_source(),
+        Some("coolstuff.js")
+    );
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,165 @@
+use sourcemap::cache::{SourceMapCache, SourceMapCacheWriter};
+use sourcemap::{DecodedMap, Error, SourceView};
+
+fn write_cache(sm: &DecodedMap, sv: Option<&SourceView<'_>>, with_sources: bool) -> Vec<u8> {
+    let mut writer = SourceMapCacheWriter::new(sm, sv).unwrap();
+    writer.set_with_source_contents(with_sources);
+    let mut buf = vec![];
+    writer.serialize(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_cache_matches_lookup_token() {
+    let input: &[_] = br#"{"version":3,"file":"test.min.js","sources":["test.js"],"names":["makeAFailure","testingStuff","Error","onSuccess","data","onFailure","invoke","cb","failed","test","value"],"mappings":"AAAA,GAAIA,cAAe,WACjB,QAASC,KACP,GAAIA,GAAe,EACnB,MAAM,IAAIC,OAAMD,GAGlB,QAASE,GAAUC,GACjBH,IAGF,QAASI,GAAUD,GACjB,KAAM,IAAIF,OAAM,WAGlB,QAASI,GAAOF,GACd,GAAIG,GAAK,IACT,IAAIH,EAAKI,OAAQ,CACfD,EAAKF,MACA,CACLE,EAAKJ,EAEPI,EAAGH,GAGL,QAASK,KACP,GAAIL,IAAQI,OAAQ,KAAME,MAAO,GACjCJ,GAAOF,GAGT,MAAOK","sourcesContent":["var makeAFailure = (function() {\n  function testingStuff() {\n    var testingStuff = 42;\n    throw new Error(testingStuff);\n  }\n\n  function onSuccess(data) {\n    testingStuff();\n  }\n\n  function onFailure(data) {\n    throw new Error('failed!');\n  }\n\n  function invoke(data) {\n    var cb = null;\n    if (data.failed) {\n      cb = onFailure;\n    } else {\n      cb = onSuccess;\n    }\n    cb(data);\n  }\n\n  function test() {\n    var data = {failed: true, value: 42};\n    invoke(data);\n  }\n\n  return test;\n})();\n"]}"#;
+    let minified_file = r#"var makeAFailure=function(){function n(){var n=42;throw new Error(n)}function r(r){n()}function e(n){throw new Error("failed!")}function i(n){var i=null;if(n.failed){i=e}else{i=r}i(n)}function u(){var n={failed:true,value:42};i(n)}return u}();"#;
+    let sv = SourceView::new(minified_file);
+    let sm = DecodedMap::from_reader(input).unwrap();
+
+    let buf = write_cache(&sm, Some(&sv), true);
+    let cache = SourceMapCache::parse(&buf).unwrap();
+    if let DecodedMap::Regular(ref sm) = sm {
+        assert_eq!(cache.get_token_count(), sm.get_token_count());
+    }
+    assert_eq!(cache.get_file_count(), 1);
+
+    for col in 0..minified_file.len() as u32 + 5 {
+        let token = sm.lookup_token(0, col).unwrap();
+        let location = cache.lookup(0, col).unwrap();
+        assert_eq!(location.get_dst_line(), token.get_dst_line());
+        assert_eq!(location.get_dst_col(), token.get_dst_col());
+        assert_eq!(location.get_source(), token.get_source());
+        assert_eq!(location.get_src_line(), token.get_src_line());
+        assert_eq!(location.get_src_col(), token.get_src_col());
+        assert_eq!(location.get_name(), token.get_name());
+    }
+
+    let location = cache.lookup(0, 107).unwrap();
+    assert_eq!(location.get_function_name(), Some("onFailure"));
+    assert_eq!(
+        location.get_source_line(),
+        Some("    throw new Error('failed!');")
+    );
+    assert_eq!(
+        cache.lookup(0, 179).unwrap().get_function_name(),
+        Some("invoke")
+    );
+    assert_eq!(
+        cache.lookup(0, 226).unwrap().get_function_name(),
+        Some("test")
+    );
+
+    // without a source view and source contents
+    let buf = write_cache(&sm, None, false);
+    let cache = SourceMapCache::parse(&buf).unwrap();
+    let location = cache.lookup(0, 107).unwrap();
+    assert_eq!(location.get_source(), Some("test.js"));
+    assert_eq!(location.get_function_name(), None);
+    assert_eq!(location.get_source_line(), None);
+}
+
+#[test]
+fn test_cache_function_scopes() {
+    let sv = SourceView::new("function a(b={}){x()}y();function(){z()}");
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["test.js"],
+        "names": ["foo"],
+        "mappings": "AAAA,SAASA,QAAQ,IAAI,eAAe"
+    }"#;
+    let sm = DecodedMap::from_reader(input).unwrap();
+    let buf = write_cache(&sm, Some(&sv), false);
+    let cache = SourceMapCache::parse(&buf).unwrap();
+
+    assert_eq!(cache.lookup(0, 0).unwrap().get_function_name(), None);
+    assert_eq!(
+        cache.lookup(0, 17).unwrap().get_function_name(),
+        Some("foo")
+    );
+    // code after the function body is not in the function
+    assert_eq!(cache.lookup(0, 21).unwrap().get_function_name(), None);
+    // code in anonymous functions has no name
+    assert_eq!(cache.lookup(0, 36).unwrap().get_function_name(), None);
+}
+
+#[test]
+fn test_cache_agrees_with_function_name_lookup() {
+    let input: &[_] = br#"{"version":3,"file":"test.min.js","sources":["test.js"],"names":["makeAFailure","testingStuff","Error","onSuccess","data","onFailure","invoke","cb","failed","test","value"],"mappings":"AAAA,GAAIA,cAAe,WACjB,QAASC,KACP,GAAIA,GAAe,EACnB,MAAM,IAAIC,OAAMD,GAGlB,QAASE,GAAUC,GACjBH,IAGF,QAASI,GAAUD,GACjB,KAAM,IAAIF,OAAM,WAGlB,QAASI,GAAOF,GACd,GAAIG,GAAK,IACT,IAAIH,EAAKI,OAAQ,CACfD,EAAKF,MACA,CACLE,EAAKJ,EAEPI,EAAGH,GAGL,QAASK,KACP,GAAIL,IAAQI,OAAQ,KAAME,MAAO,GACjCJ,GAAOF,GAGT,MAAOK"}"#;
+    let sv = SourceView::new(
+        r#"var makeAFailure=function(){function n(){var n=42;throw new Error(n)}function r(r){n()}function e(n){throw new Error("failed!")}function i(n){var i=null;if(n.failed){i=e}else{i=r}i(n)}function u(){var n={failed:true,value:42};i(n)}return u}();"#,
+    );
+    let sm = DecodedMap::from_reader(input).unwrap();
+    let buf = write_cache(&sm, Some(&sv), false);
+    let cache = SourceMapCache::parse(&buf).unwrap();
+
+    for (col, minified_name) in [(107, "e"), (179, "i"), (226, "u"), (84, "r")] {
+        let expected = sm.get_original_function_name(0, col, Some(minified_name), Some(&sv));
+        assert!(expected.is_some());
+        assert_eq!(cache.lookup(0, col).unwrap().get_function_name(), expected);
+    }
+
+    // neither path names arrow functions and methods
+    let sv = SourceView::new("var a=()=>{x()};var o={m(){y()}};");
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["test.js"],
+        "names": ["foo", "bar"],
+        "mappings": "IAAIA,OAAO,YAAYC,IAAI"
+    }"#;
+    let sm = DecodedMap::from_reader(input).unwrap();
+    let buf = write_cache(&sm, Some(&sv), false);
+    let cache = SourceMapCache::parse(&buf).unwrap();
+    for (col, minified_name) in [(11, "a"), (27, "m")] {
+        assert_eq!(
+            sm.get_original_function_name(0, col, Some(minified_name), Some(&sv)),
+            None
+        );
+        assert_eq!(cache.lookup(0, col).unwrap().get_function_name(), None);
+    }
+}
+
+#[test]
+fn test_cache_hermes_function_names() {
+    let input: &[_] = include_bytes!("./fixtures/react-native-hermes/output.map");
+    let sm = DecodedMap::from_reader(input).unwrap();
+    let buf = write_cache(&sm, None, false);
+    let cache = SourceMapCache::parse(&buf).unwrap();
+
+    let location = cache.lookup(0, 11939).unwrap();
+    assert_eq!(location.get_source(), Some("module.js"));
+    assert_eq!(location.get_src_line(), 1);
+    assert_eq!(location.get_src_col(), 10);
+    assert_eq!(location.get_function_name(), Some("foo"));
+
+    let location = cache.lookup(0, 11857).unwrap();
+    assert_eq!(location.get_source(), Some("input.js"));
+    assert_eq!(location.get_function_name(), Some("<global>"));
+}
+
+#[test]
+fn test_cache_invalid_data() {
+    let input: &[_] = br#"{"version":3,"sources":["a.js"],"names":[],"mappings":"AAAA"}"#;
+    let sm = DecodedMap::from_reader(input).unwrap();
+    let buf = write_cache(&sm, None, false);
+
+    assert!(matches!(
+        SourceMapCache::parse(b"nope"),
+        Err(Error::InvalidCacheData)
+    ));
+    let mut bad_magic = buf.clone();
+    bad_magic[0] = b'X';
+    assert!(matches!(
+        SourceMapCache::parse(&bad_magic),
+        Err(Error::InvalidCacheMagic)
+    ));
+    let mut bad_version = buf.clone();
+    bad_version[4] = 42;
+    assert!(matches!(
+        SourceMapCache::parse(&bad_version),
+        Err(Error::UnsupportedCacheVersion(42))
+    ));
+    assert!(matches!(
+        SourceMapCache::parse(&buf[..buf.len() - 1]),
+        Err(Error::InvalidCacheData)
+    ));
+}
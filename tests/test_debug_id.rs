@@ -0,0 +1,218 @@
+use sourcemap::debug_id::{debug_id_from_source, inject_debug_id, InjectOptions};
+use sourcemap::{DecodedMap, SourceKind, SourceMap, SourceView};
+
+const MAP: &[u8] = br#"{
+    "version": 3,
+    "sources": ["coolstuff.js"],
+    "names": ["x", "alert"],
+    "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+}"#;
+
+fn encode(sm: &DecodedMap) -> String {
+    let mut out = vec![];
+    sm.to_writer(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_inject_comment() {
+    let source = SourceView::new("var x = 1;\nalert(x);\n");
+    let mut sm = DecodedMap::from_reader(MAP).unwrap();
+    let before = sm.lookup_token(0, 4).unwrap().to_owned_token();
+
+    let injected = inject_debug_id(&source, &mut sm, &InjectOptions::default());
+    let debug_id = injected.get_debug_id();
+    assert!(injected.is_modified());
+    assert_eq!(debug_id, debug_id_from_source(source.source()));
+    assert_eq!(
+        injected.get_source(),
+        format!("var x = 1;\nalert(x);\n//# debugId={}\n", debug_id)
+    );
+    assert_eq!(sm.get_debug_id(), Some(debug_id));
+    assert_eq!(sm.lookup_token(0, 4).unwrap().to_owned_token(), before);
+    assert!(encode(&sm).contains(&format!(r#""debugId":"{}""#, debug_id)));
+}
+
+#[test]
+fn test_inject_runtime_snippet() {
+    let source = SourceView::new("var x = 1;\nalert(x);\n");
+    let mut sm = DecodedMap::from_reader(MAP).unwrap();
+    let options = InjectOptions {
+        runtime_snippet: true,
+        ..Default::default()
+    };
+
+    let injected = inject_debug_id(&source, &mut sm, &options);
+    let lines: Vec<_> = injected.get_source().lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].contains(&injected.get_debug_id().to_string()));
+    assert_eq!(lines[1], "var x = 1;");
+    assert_eq!(lines[2], "alert(x);");
+
+    let sm = match sm {
+        DecodedMap::Regular(sm) => sm,
+        _ => unreachable!(),
+    };
+    let original = SourceMap::from_slice(MAP).unwrap();
+    for (a, b) in original.tokens().zip(sm.tokens()) {
+        assert_eq!(a.get_dst_line() + 1, b.get_dst_line());
+        assert_eq!(a.get_dst_col(), b.get_dst_col());
+        assert_eq!(a.get_src(), b.get_src());
+    }
+    assert_eq!(
+        sm.lookup_token(1, 4).unwrap().to_tuple(),
+        original.lookup_token(0, 4).unwrap().to_tuple()
+    );
+}
+
+#[test]
+fn test_inject_after_prologue() {
+    let source = SourceView::new("#!/usr/bin/env node\n\"use strict\";\nfoo();");
+    let mut sm = DecodedMap::from_reader(MAP).unwrap();
+    let options = InjectOptions {
+        runtime_snippet: true,
+        ..Default::default()
+    };
+
+    let injected = inject_debug_id(&source, &mut sm, &options);
+    let lines: Vec<_> = injected.get_source().lines().collect();
+    assert_eq!(lines[0], "#!/usr/bin/env node");
+    assert_eq!(lines[1], "\"use strict\";");
+    assert!(lines[2].starts_with("!function(){"));
+    assert_eq!(lines[3], "foo();");
+    assert!(lines[4].starts_with("//# debugId="));
+    // the map only covers the first line which stays in place
+    assert_eq!(sm.lookup_token(0, 4).unwrap().get_dst(), (0, 3));
+
+    // a prologue on a line with code cannot be moved past
+    let source = SourceView::new("\"use strict\";foo();");
+    let mut sm = DecodedMap::from_reader(MAP).unwrap();
+    let injected = inject_debug_id(&source, &mut sm, &options);
+    let lines: Vec<_> = injected.get_source().lines().collect();
+    assert_eq!(lines[0], "\"use strict\";foo();");
+    assert!(lines[1].starts_with("!function(){"));
+    assert!(lines[2].starts_with("//# debugId="));
+    assert_eq!(sm.lookup_token(0, 4).unwrap().get_dst(), (0, 3));
+}
+
+#[test]
+fn test_inject_index() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "map": {"version": 3, "sources": ["a.js"], "names": [], "mappings": "AAAA;AACA"}
+            },
+            {
+                "offset": {"line": 2, "column": 0},
+                "map": {"version": 3, "sources": ["b.js"], "names": [], "mappings": "AAAA"}
+            }
+        ]
+    }"#;
+    let source = SourceView::new("a();\na();\nb();\n");
+    let mut sm = DecodedMap::from_reader(input).unwrap();
+    let options = InjectOptions {
+        runtime_snippet: true,
+        ..Default::default()
+    };
+    let injected = inject_debug_id(&source, &mut sm, &options);
+
+    let positions: Vec<_> = (0..4)
+        .map(|line| {
+            sm.lookup_token(line, 0)
+                .map(|t| (t.get_source().unwrap().to_owned(), t.get_src_line()))
+        })
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            None,
+            Some(("a.js".to_owned(), 0)),
+            Some(("a.js".to_owned(), 1)),
+            Some(("b.js".to_owned(), 0)),
+        ]
+    );
+    assert_eq!(sm.get_debug_id(), Some(injected.get_debug_id()));
+}
+
+#[test]
+fn test_inject_index_with_url_section() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "url": "a.js.map"
+            },
+            {
+                "offset": {"line": 2, "column": 0},
+                "map": {"version": 3, "sources": ["b.js"], "names": [], "mappings": "AAAA"}
+            }
+        ]
+    }"#;
+    let source = SourceView::new("\"use strict\";\na();\nb();");
+    let mut sm = DecodedMap::from_reader(input).unwrap();
+    let options = InjectOptions {
+        runtime_snippet: true,
+        ..Default::default()
+    };
+    let injected = inject_debug_id(&source, &mut sm, &options);
+
+    // the first section cannot be moved so the snippet goes to the end
+    let lines: Vec<_> = injected.get_source().lines().collect();
+    assert_eq!(&lines[..3], ["\"use strict\";", "a();", "b();"]);
+    assert!(lines[3].starts_with("!function(){"));
+    assert!(lines[4].starts_with("//# debugId="));
+    assert_eq!(
+        sm.lookup_token(2, 0).and_then(|t| t.get_source()),
+        Some("b.js")
+    );
+}
+
+#[test]
+fn test_inject_css_ignores_runtime_snippet() {
+    let source = SourceView::new("a{color:red}");
+    let mut sm = DecodedMap::from_reader(MAP).unwrap();
+    let options = InjectOptions {
+        runtime_snippet: true,
+        kind: SourceKind::Css,
+    };
+    let injected = inject_debug_id(&source, &mut sm, &options);
+
+    let lines: Vec<_> = injected.get_source().lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "a{color:red}");
+    assert!(lines[1].starts_with("/*# debugId="));
+    // nothing was inserted before the code
+    assert_eq!(sm.lookup_token(0, 4).unwrap().get_dst(), (0, 3));
+    assert_eq!(sm.get_debug_id(), Some(injected.get_debug_id()));
+}
+
+#[test]
+fn test_inject_twice() {
+    let source = SourceView::new("var x = 1;\nalert(x);");
+    let mut sm = DecodedMap::from_reader(MAP).unwrap();
+    let options = InjectOptions {
+        runtime_snippet: true,
+        ..Default::default()
+    };
+    let first = inject_debug_id(&source, &mut sm, &options);
+    let first_map = encode(&sm);
+
+    let source = SourceView::new(first.get_source());
+    let second = inject_debug_id(&source, &mut sm, &options);
+    assert!(!second.is_modified());
+    assert_eq!(second.get_source(), first.get_source());
+    assert_eq!(second.get_debug_id(), first.get_debug_id());
+    assert_eq!(encode(&sm), first_map);
+
+    // the same input always yields the same ID
+    let mut other = DecodedMap::from_reader(MAP).unwrap();
+    let third = inject_debug_id(
+        &SourceView::new("var x = 1;\nalert(x);"),
+        &mut other,
+        &options,
+    );
+    assert_eq!(third, first);
+}
@@ -142,3 +142,72 @@ fn test_sourcemap_nofiles() {
     assert_eq!(iter.next().unwrap().to_tuple(), ("", 2, 2, Some("alert")));
     assert!(iter.next().is_none());
 }
+
+#[test]
+fn test_sourcemap_data_url_roundtrip() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["coolstuff.js"],
+        "names": ["x","alert"],
+        "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+    let url = sm.to_data_url().unwrap();
+    assert!(url.starts_with("data:application/json;base64,"));
+
+    let decoded = decode_data_url(&url).unwrap();
+    assert_eq!(decoded.to_data_url().unwrap(), url);
+    match decoded {
+        DecodedMap::Regular(sm2) => {
+            assert_eq!(
+                sm2.tokens().collect::<Vec<_>>(),
+                sm.tokens().collect::<Vec<_>>()
+            );
+        }
+        _ => panic!("did not get sourcemap"),
+    }
+
+    let url = "data:application/json;charset=utf-8,%7B%22version%22%3A3%2C%22sources%22%3A%5B%22a.js%22%5D%2C%22names%22%3A%5B%5D%2C%22mappings%22%3A%22AAAA%22%7D";
+    match decode_data_url(url).unwrap() {
+        DecodedMap::Regular(sm) => assert_eq!(sm.get_source(0), Some("a.js")),
+        _ => panic!("did not get sourcemap"),
+    }
+}
+
+#[test]
+fn test_sourcemap_debug_id() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": [],
+        "names": [],
+        "mappings": "",
+        "debug_id": "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"
+    }"#;
+    let sm = SourceMap::from_slice(input).unwrap();
+    assert_eq!(
+        sm.get_debug_id().unwrap().to_string(),
+        "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"
+    );
+
+    let mut out = vec![];
+    sm.to_writer(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains(r#""debugId":"b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab""#));
+    let sm = SourceMap::from_slice(out.as_bytes()).unwrap();
+    assert_eq!(
+        sm.get_debug_id().unwrap().to_string(),
+        "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"
+    );
+}
+
+#[test]
+fn test_sourcemap_invalid_debug_id() {
+    for debug_id in [r#""not-a-uuid""#, r#""""#, "42", "null", "{}"] {
+        let input = format!(
+            r#"{{"version":3,"sources":["a.js"],"names":[],"mappings":"AAAA","debugId":{debug_id}}}"#
+        );
+        let sm = SourceMap::from_slice(input.as_bytes()).unwrap();
+        assert_eq!(sm.get_debug_id(), None, "debug ID {debug_id}");
+        assert_eq!(sm.get_token_count(), 1);
+    }
+}
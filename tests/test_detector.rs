@@ -1,4 +1,9 @@
-use sourcemap::{is_sourcemap_slice, locate_sourcemap_reference, SourceMapRef};
+use sourcemap::{
+    append_sourcemap_reference, is_sourcemap_slice, locate_debug_id, locate_source_references,
+    locate_source_url, locate_sourcemap_comment, locate_sourcemap_reference,
+    replace_sourcemap_reference, sourcemap_comment_candidates, strip_sourcemap_reference,
+    SourceKind, SourceMapRef, SourceView,
+};
 
 #[test]
 fn test_basic_locate() {
@@ -69,3 +74,285 @@ fn test_detect_basic_sourcemap_with_junk_header() {
     }"#;
     assert!(is_sourcemap_slice(input));
 }
+
+#[test]
+fn test_locate_comment() {
+    let input =
+        "foo();\n//# sourceMappingURL=old.js.map\nbar();\n  //# sourceMappingURL=foo.js.map  \r\n";
+    let comment = locate_sourcemap_comment(input.as_bytes()).unwrap();
+    assert_eq!(comment.get_ref(), &SourceMapRef::Ref("foo.js.map".into()));
+    assert_eq!(comment.get_kind(), SourceKind::JavaScript);
+    assert_eq!(
+        &input[comment.get_range()],
+        "//# sourceMappingURL=foo.js.map"
+    );
+    assert_eq!(&input[comment.get_url_range()], "foo.js.map");
+
+    let input = "a{}\n/*@ sourceMappingURL= style.css.map */\n";
+    let comment = locate_sourcemap_comment(input.as_bytes()).unwrap();
+    assert_eq!(
+        comment.into_ref(),
+        SourceMapRef::LegacyRef("style.css.map".into())
+    );
+
+    assert_eq!(locate_sourcemap_comment(b"foo();\n// whatever"), None);
+}
+
+#[test]
+fn test_replace_reference() {
+    assert_eq!(
+        replace_sourcemap_reference("foo();\n//@ sourceMappingURL=foo.js.map\n", "bar.js.map")
+            .unwrap(),
+        "foo();\n//# sourceMappingURL=bar.js.map\n"
+    );
+    assert_eq!(
+        replace_sourcemap_reference(
+            "a{}\n/*# sourceMappingURL=a.css.map */",
+            "data:application/json;base64,e30="
+        )
+        .unwrap(),
+        "a{}\n/*# sourceMappingURL=data:application/json;base64,e30= */"
+    );
+    assert_eq!(replace_sourcemap_reference("foo();\n", "bar.js.map"), None);
+}
+
+#[test]
+fn test_strip_reference() {
+    assert_eq!(
+        strip_sourcemap_reference("foo();\n//# sourceMappingURL=foo.js.map\n"),
+        "foo();\n"
+    );
+    assert_eq!(
+        strip_sourcemap_reference("foo();\n//# sourceMappingURL=foo.js.map"),
+        "foo();"
+    );
+    assert_eq!(
+        strip_sourcemap_reference("foo();\r\n//# sourceMappingURL=foo.js.map\r\nbar();\r\n"),
+        "foo();\r\nbar();\r\n"
+    );
+    assert_eq!(
+        strip_sourcemap_reference("a{}\n/*# sourceMappingURL=a.css.map */ \n"),
+        "a{}\n"
+    );
+    assert_eq!(
+        strip_sourcemap_reference("a\r\n//# sourceMappingURL=x.map"),
+        "a"
+    );
+    assert_eq!(
+        strip_sourcemap_reference("a\r//# sourceMappingURL=x.map"),
+        "a"
+    );
+    assert_eq!(
+        strip_sourcemap_reference("a\r\n//# sourceMappingURL=x.map  \r\nb\r\n"),
+        "a\r\nb\r\n"
+    );
+    assert_eq!(strip_sourcemap_reference("foo();\n"), "foo();\n");
+}
+
+#[test]
+fn test_append_reference() {
+    assert_eq!(
+        append_sourcemap_reference("foo();\n", "foo.js.map", SourceKind::JavaScript),
+        "foo();\n//# sourceMappingURL=foo.js.map\n"
+    );
+    assert_eq!(
+        append_sourcemap_reference(
+            "foo();\n//# sourceMappingURL=old.js.map",
+            "foo.js.map",
+            SourceKind::JavaScript
+        ),
+        "foo();\n//# sourceMappingURL=foo.js.map"
+    );
+    assert_eq!(
+        append_sourcemap_reference("a{}\r\n\r\n", "a.css.map", SourceKind::Css),
+        "a{}\r\n/*# sourceMappingURL=a.css.map */\r\n"
+    );
+    assert_eq!(
+        append_sourcemap_reference("", "a.js.map", SourceKind::JavaScript),
+        "//# sourceMappingURL=a.js.map"
+    );
+}
+
+#[test]
+fn test_css_locate() {
+    let input: &[_] = b".foo{color:red}\n/*# sourceMappingURL=foo.css.map */\n";
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::Ref("foo.css.map".into()))
+    );
+
+    // minified stylesheets often put the comment after the rules
+    let input: &[_] = b".foo{color:red}/*# sourceMappingURL=foo.css.map*/";
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::Ref("foo.css.map".into()))
+    );
+    let comment = locate_sourcemap_comment(input).unwrap();
+    assert_eq!(comment.get_kind(), SourceKind::Css);
+    assert_eq!(comment.get_range(), 15..input.len());
+    assert_eq!(
+        strip_sourcemap_reference(std::str::from_utf8(input).unwrap()),
+        ".foo{color:red}"
+    );
+
+    let input: &[_] = b"/* a comment */ .a{} /*@ sourceMappingURL=legacy.css.map */";
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::LegacyRef("legacy.css.map".into()))
+    );
+
+    let sv = sourcemap::SourceView::new(".a{}\n/*# sourceMappingURL=a.css.map */");
+    assert_eq!(
+        sv.sourcemap_reference().unwrap(),
+        Some(SourceMapRef::Ref("a.css.map".into()))
+    );
+}
+
+#[test]
+fn test_last_reference_wins() {
+    let input: &[_] =
+        b"//# sourceMappingURL=embedded.js.map\nfoo();\n//# sourceMappingURL=bundle.js.map\n";
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::Ref("bundle.js.map".into()))
+    );
+
+    let urls: Vec<_> = sourcemap_comment_candidates(input)
+        .map(|comment| comment.get_ref().get_url().to_string())
+        .collect();
+    assert_eq!(urls, vec!["bundle.js.map", "embedded.js.map"]);
+}
+
+#[test]
+fn test_reference_in_string_literal() {
+    let input: &[_] = b"//# sourceMappingURL=real.js.map\nvar a = \"//# sourceMappingURL=fake.js.map\";\nvar b = '/*# sourceMappingURL=fake.css.map */';\n";
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::Ref("real.js.map".into()))
+    );
+    assert_eq!(sourcemap_comment_candidates(input).count(), 1);
+
+    // escaped quotes do not end the string
+    let input: &[_] = b"var a = \"\\\" //# sourceMappingURL=fake.js.map\";";
+    assert_eq!(locate_sourcemap_reference(input).unwrap(), None);
+}
+
+#[test]
+fn test_reference_after_code() {
+    let input: &[_] = b"foo(); //# sourceMappingURL=foo.js.map";
+    let comment = locate_sourcemap_comment(input).unwrap();
+    assert_eq!(comment.get_ref(), &SourceMapRef::Ref("foo.js.map".into()));
+    assert_eq!(comment.get_range(), 7..input.len());
+
+    // urls in CSS are not line comments
+    let input: &[_] =
+        b"a{background:url(http://example.com/a.png)}/*# sourceMappingURL=a.css.map */";
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::Ref("a.css.map".into()))
+    );
+
+    // regular comments are not references
+    let input: &[_] = b"foo(); // see //# sourceMappingURL=foo.js.map";
+    assert_eq!(locate_sourcemap_reference(input).unwrap(), None);
+}
+
+#[test]
+fn test_source_url() {
+    let input: &[_] = b"eval('x');\n//# sourceURL=webpack://app/./src/foo.js";
+    assert_eq!(
+        locate_source_url(input).as_deref(),
+        Some("webpack://app/./src/foo.js")
+    );
+    assert_eq!(locate_sourcemap_reference(input).unwrap(), None);
+
+    let input: &[_] = b"//@ sourceURL=old.js\n//# sourceURL=new.js\n";
+    assert_eq!(locate_source_url(input).as_deref(), Some("new.js"));
+
+    let input: &[_] = b"a { color: red }\n/*# sourceURL=foo.css */";
+    assert_eq!(locate_source_url(input).as_deref(), Some("foo.css"));
+
+    let input: &[_] = b"var s = '//# sourceURL=nope.js';";
+    assert_eq!(locate_source_url(input), None);
+}
+
+#[test]
+fn test_source_references() {
+    let input = "new Function('x');\n//# sourceMappingURL=foo.js.map\n//# sourceURL=foo.js\n";
+    let refs = locate_source_references(input.as_bytes());
+    assert_eq!(refs.get_source_url(), Some("foo.js"));
+    assert_eq!(
+        refs.get_sourcemap_ref(),
+        Some(&SourceMapRef::Ref("foo.js.map".into()))
+    );
+
+    let sv = SourceView::new(input);
+    assert_eq!(sv.source_url().as_deref(), Some("foo.js"));
+    assert_eq!(sv.source_references(), refs);
+    assert_eq!(
+        sv.sourcemap_reference().unwrap(),
+        Some(SourceMapRef::Ref("foo.js.map".into()))
+    );
+
+    let refs = locate_source_references(b"foo();");
+    assert_eq!(refs.get_source_url(), None);
+    assert_eq!(refs.get_sourcemap_ref(), None);
+}
+
+#[test]
+fn test_from_headers() {
+    let headers = [
+        ("Content-Type", "application/javascript"),
+        ("X-SourceMap", "legacy.js.map"),
+        ("sourcemap", " /maps/app.js.map "),
+    ];
+    let reference = SourceMapRef::from_headers(headers.iter().copied()).unwrap();
+    assert_eq!(reference, SourceMapRef::Header("/maps/app.js.map".into()));
+    assert_eq!(
+        reference
+            .resolve("https://example.com/static/app.js")
+            .as_deref(),
+        Some("https://example.com/maps/app.js.map")
+    );
+
+    let reference = SourceMapRef::from_headers(vec![("x-sourcemap", "app.js.map")]).unwrap();
+    assert_eq!(
+        reference
+            .resolve("https://example.com/static/app.js")
+            .as_deref(),
+        Some("https://example.com/static/app.js.map")
+    );
+
+    assert_eq!(
+        SourceMapRef::from_headers(vec![("Content-Length", "42")]),
+        None
+    );
+}
+
+#[test]
+fn test_header_over_comment() {
+    let source = b"foo();\n//# sourceMappingURL=comment.js.map";
+    assert_eq!(
+        SourceMapRef::from_headers_and_source(vec![("SourceMap", "header.js.map")], source)
+            .unwrap(),
+        Some(SourceMapRef::Header("header.js.map".into()))
+    );
+    assert_eq!(
+        SourceMapRef::from_headers_and_source(Vec::<(&str, &str)>::new(), source).unwrap(),
+        Some(SourceMapRef::Ref("comment.js.map".into()))
+    );
+}
+
+#[test]
+fn test_locate_debug_id() {
+    let input: &[_] = b"foo();\n//# debugId=B1E7AB8A-36A3-4AC1-9A0E-C7B2A9C3B0AB\n//# sourceMappingURL=foo.js.map";
+    assert_eq!(
+        locate_debug_id(input).unwrap().to_string(),
+        "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"
+    );
+    assert_eq!(
+        locate_sourcemap_reference(input).unwrap(),
+        Some(SourceMapRef::Ref("foo.js.map".into()))
+    );
+    assert_eq!(locate_debug_id(b"//# debugId=nope"), None);
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sourcemap::discovery::{discover, DiscoveredMap, MatchKind};
+use sourcemap::{SourceKind, SourceMapRef};
+
+const MAP: &str = r#"{"version":3,"sources":["a.js"],"names":[],"mappings":"AAAA"}"#;
+
+fn setup(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!(
+        "sourcemap-discovery-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&root);
+    for (path, contents) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    fs::canonicalize(root).unwrap()
+}
+
+fn file_names(root: &Path, paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|p| {
+            p.strip_prefix(root)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .replace('\\', "/")
+        })
+        .collect()
+}
+
+#[test]
+fn test_discover() {
+    let root = setup(
+        "basic",
+        &[
+            ("app.js", "app();\n//# sourceMappingURL=maps/app.map\n"),
+            ("maps/app.map", MAP),
+            ("vendor.mjs", "vendor();"),
+            ("vendor.mjs.map", MAP),
+            ("style.css", "a{}\n/*# sourceMappingURL=gone.css.map */"),
+            (
+                "inline.cjs",
+                "x();\n//# sourceMappingURL=data:application/json;base64,e30=",
+            ),
+            (
+                "chunks/1.js",
+                "chunk();\n//# debugId=b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab\n",
+            ),
+            (
+                "chunks/bundle.map",
+                r#"{"version":3,"sources":[],"names":[],"mappings":"","debugId":"b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"}"#,
+            ),
+            ("unused.js.map", MAP),
+            ("readme.txt", "hello"),
+        ],
+    );
+
+    let discovery = discover(&root).unwrap();
+    let pairs: Vec<_> = discovery
+        .pairs()
+        .iter()
+        .map(|pair| {
+            (
+                pair.get_minified_path()
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .replace('\\', "/"),
+                pair.get_match_kind(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        pairs,
+        vec![
+            ("app.js".to_string(), MatchKind::Reference),
+            ("chunks/1.js".to_string(), MatchKind::DebugId),
+            ("inline.cjs".to_string(), MatchKind::DataUrl),
+            ("vendor.mjs".to_string(), MatchKind::Sibling),
+        ]
+    );
+
+    let app = &discovery.pairs()[0];
+    assert_eq!(app.get_kind(), SourceKind::JavaScript);
+    assert_eq!(app.get_sourcemap_path(), Some(&*root.join("maps/app.map")));
+    let chunk = &discovery.pairs()[1];
+    assert_eq!(
+        chunk.get_debug_id().unwrap().to_string(),
+        "b1e7ab8a-36a3-4ac1-9a0e-c7b2a9c3b0ab"
+    );
+    assert_eq!(
+        chunk.get_sourcemap_path(),
+        Some(&*root.join("chunks/bundle.map"))
+    );
+    match discovery.pairs()[2].get_sourcemap() {
+        DiscoveredMap::Embedded(reference) => {
+            assert!(reference.get_embedded_sourcemap().unwrap().is_some())
+        }
+        other => panic!("unexpected map {:?}", other),
+    }
+
+    assert_eq!(
+        file_names(&root, discovery.unpaired_files()),
+        vec!["style.css"]
+    );
+    assert_eq!(discovery.missing_references().len(), 1);
+    let missing = &discovery.missing_references()[0];
+    assert_eq!(missing.get_minified_path(), root.join("style.css"));
+    assert_eq!(missing.get_ref(), &SourceMapRef::Ref("gone.css.map".into()));
+    assert_eq!(
+        missing.get_resolved_path(),
+        Some(&*root.join("gone.css.map"))
+    );
+    assert_eq!(
+        file_names(&root, discovery.orphan_maps()),
+        vec!["unused.js.map"]
+    );
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_missing_reference_falls_back_to_sibling() {
+    let root = setup(
+        "fallback",
+        &[
+            ("app.js", "app();\n//# sourceMappingURL=missing.map"),
+            ("app.js.map", MAP),
+        ],
+    );
+
+    let discovery = discover(&root).unwrap();
+    assert_eq!(discovery.pairs().len(), 1);
+    assert_eq!(discovery.pairs()[0].get_match_kind(), MatchKind::Sibling);
+    assert_eq!(discovery.missing_references().len(), 1);
+    assert!(discovery.orphan_maps().is_empty());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -1,4 +1,4 @@
-use sourcemap::SourceMap;
+use sourcemap::{EncodeOptions, Error, MissingSourceContents, SourceMap, SourceMapIndex};
 
 #[test]
 fn test_basic_sourcemap() {
@@ -18,3 +18,165 @@ fn test_basic_sourcemap() {
         assert_eq!(tok1, tok2);
     }
 }
+
+fn encode_with(sm: &SourceMap, options: &EncodeOptions<'_>) -> String {
+    let mut out: Vec<u8> = vec![];
+    sm.to_writer_with(&mut out, options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_encode_options() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "file": "min.js",
+        "sources": ["src/coolstuff.js", "lib/other.js"],
+        "sourcesContent": ["var x = 1;", null],
+        "names": ["x","alert"],
+        "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+
+    let mut out: Vec<u8> = vec![];
+    sm.to_writer(&mut out).unwrap();
+    assert_eq!(
+        encode_with(&sm, &Default::default()),
+        String::from_utf8(out).unwrap()
+    );
+
+    // not all sources are below the root
+    let mut out: Vec<u8> = vec![];
+    let rv = sm.to_writer_with(
+        &mut out,
+        &EncodeOptions {
+            source_root: Some("src"),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(rv, Err(Error::SourceOutsideRoot(ref source)) if source == "lib/other.js"));
+
+    let output = encode_with(
+        &sm,
+        &EncodeOptions {
+            with_names: false,
+            with_source_contents: false,
+            spec_key_order: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        output,
+        r#"{"version":3,"file":"min.js","sources":["src/coolstuff.js","lib/other.js"],"mappings":"AAAA,GAAI,GAAI,EACR,IAAI,GAAK,EAAG,CACV,MAAM"}"#
+    );
+    let sm2 = SourceMap::from_slice(output.as_bytes()).unwrap();
+    assert_eq!(sm2.get_token_count(), sm.get_token_count());
+    assert!(sm2.tokens().all(|token| token.get_name().is_none()));
+    assert!(sm2.sources().eq(sm.sources()));
+
+    let output = encode_with(
+        &sm,
+        &EncodeOptions {
+            with_source_contents: false,
+            source_root: Some(""),
+            ..Default::default()
+        },
+    );
+    assert!(output.contains(r#""sourceRoot":"""#));
+    assert!(output.contains(r#""sources":["src/coolstuff.js","lib/other.js"]"#));
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["src/coolstuff.js", "src/lib/other.js"],
+        "names": [],
+        "mappings": "AAAA,CCAA"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+    let output = encode_with(
+        &sm,
+        &EncodeOptions {
+            source_root: Some("src/"),
+            spec_key_order: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        output,
+        r#"{"version":3,"sourceRoot":"src/","sources":["coolstuff.js","lib/other.js"],"names":[],"mappings":"AAAA,CCAA"}"#
+    );
+    let sm2 = SourceMap::from_slice(output.as_bytes()).unwrap();
+    assert!(sm2.sources().eq(sm.sources()));
+
+    let output = encode_with(
+        &sm,
+        &EncodeOptions {
+            pretty: true,
+            ..Default::default()
+        },
+    );
+    assert!(output.starts_with("{\n  \"version\": 3,\n"));
+    assert_eq!(
+        encode_with(
+            &sm,
+            &EncodeOptions {
+                pretty: true,
+                ..Default::default()
+            },
+        ),
+        output
+    );
+}
+
+#[test]
+fn test_encode_missing_source_contents() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["a.js", "b.js"],
+        "names": [],
+        "mappings": "AAAA,CCAA"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+
+    assert!(!encode_with(&sm, &Default::default()).contains("sourcesContent"));
+    assert!(encode_with(
+        &sm,
+        &EncodeOptions {
+            missing_source_contents: MissingSourceContents::Null,
+            ..Default::default()
+        }
+    )
+    .contains(r#""sourcesContent":[null,null]"#));
+}
+
+#[test]
+fn test_encode_index_with_options() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "file": "min.js",
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "map": {
+                    "version":3,
+                    "sources":["file1.js"],
+                    "names":["add"],
+                    "mappings":"AAAAA"
+                }
+            }
+        ]
+    }"#;
+    let smi = SourceMapIndex::from_reader(input).unwrap();
+    let mut out: Vec<u8> = vec![];
+    smi.to_writer_with(
+        &mut out,
+        &EncodeOptions {
+            with_names: false,
+            spec_key_order: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"{"version":3,"file":"min.js","sections":[{"offset":{"line":0,"column":0},"url":null,"map":{"version":3,"sources":["file1.js"],"mappings":"AAAA"}}]}"#
+    );
+}
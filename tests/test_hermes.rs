@@ -40,3 +40,30 @@ fn test_react_native_metro() {
     assert_eq!(token.to_tuple(), ("input.js", 2, 0, None));
     assert_eq!(sm.get_scope_for_token(token), Some("<global>"));
 }
+
+#[test]
+fn test_sourcemap_like() {
+    use sourcemap::{DecodedMap, SourceMapLike};
+
+    fn function_name<M: SourceMapLike>(map: &M, line: u32, col: u32) -> Option<&str> {
+        map.original_function_name(line, col, None, None)
+    }
+
+    let input: &[_] = include_bytes!("./fixtures/react-native-metro/output.js.map");
+    let sm = SourceMapHermes::from_reader(input).unwrap();
+    assert_eq!(function_name(&sm, 1288, 10), Some("foo"));
+    assert_eq!(function_name(&sm, 1279, 18), Some("<global>"));
+
+    let dm = DecodedMap::from_reader(input).unwrap();
+    assert!(matches!(dm, DecodedMap::Hermes(_)));
+    assert_eq!(function_name(&dm, 1288, 10), Some("foo"));
+    assert_eq!(
+        dm.get_original_function_name(1288, 10, None, None),
+        Some("foo")
+    );
+    assert_eq!(
+        SourceMapLike::sources(&dm).collect::<Vec<_>>(),
+        sm.sources().collect::<Vec<_>>()
+    );
+    assert_eq!(SourceMapLike::tokens(&dm).count(), sm.tokens().count());
+}
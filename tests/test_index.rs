@@ -158,3 +158,205 @@ fn test_indexed_sourcemap_for_react_native() {
     let ism = SourceMapIndex::from_reader(input).unwrap();
     assert!(ism.is_for_ram_bundle());
 }
+
+#[test]
+fn test_indexed_sourcemap_lookup_tokens() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "file": "min.js",
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "map": {
+                    "version":3,
+                    "sources":["file1.js"],
+                    "names":["add","a","b"],
+                    "mappings":"AAAA,QAASA,KAAIC,EAAGC,GACf,YACA,OAAOD,GAAIC"
+                }
+            },
+            {
+                "offset": {"line": 1, "column": 1},
+                "map": {
+                    "version":3,
+                    "sources":["file2.js"],
+                    "names":["multiply","a","b","divide","add","c","e","Raven","captureException"],
+                    "mappings":"AAAA,QAASA,UAASC,EAAGC,GACpB,YACA,OAAOD,GAAIC,EAEZ,QAASC,QAAOF,EAAGC,GAClB,YACA,KACC,MAAOF,UAASI,IAAIH,EAAGC,GAAID,EAAGC,GAAKG,EAClC,MAAOC,GACRC,MAAMC,iBAAiBF"
+                }
+            }
+        ]
+    }"#;
+    let ism = DecodedMap::from_reader(input).unwrap();
+
+    let positions: Vec<_> = (0..5)
+        .rev()
+        .flat_map(|line| (0..120).step_by(7).map(move |col| (line, col)))
+        .collect();
+    let expected: Vec<_> = positions
+        .iter()
+        .map(|&(line, col)| ism.lookup_token(line, col))
+        .collect();
+    assert_eq!(ism.lookup_tokens(&positions), expected);
+    assert!(ism.lookup_tokens(&[]).is_empty());
+}
+
+#[test]
+fn test_indexed_sourcemap_lookup_token_with_options() {
+    use sourcemap::{Bias, LookupOptions};
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 2},
+                "map": {
+                    "version":3,
+                    "sources":["file1.js"],
+                    "names":[],
+                    "mappings":"AAAA,EAAC"
+                }
+            },
+            {
+                "offset": {"line": 1, "column": 5},
+                "map": {
+                    "version":3,
+                    "sources":["file2.js"],
+                    "names":[],
+                    "mappings":"EAAA"
+                }
+            }
+        ]
+    }"#;
+    let ism = SourceMapIndex::from_reader(input).unwrap();
+    let lub = LookupOptions {
+        bias: Bias::LeastUpperBound,
+        ..Default::default()
+    };
+    fn source(token: Option<sourcemap::Token<'_>>) -> Option<&str> {
+        token.and_then(|t| t.get_source())
+    }
+
+    assert_eq!(source(ism.lookup_token_with(0, 0, lub)), Some("file1.js"));
+    assert_eq!(source(ism.lookup_token_with(0, 9, lub)), Some("file2.js"));
+    assert_eq!(source(ism.lookup_token_with(1, 7, lub)), Some("file2.js"));
+    assert_eq!(source(ism.lookup_token_with(1, 8, lub)), None);
+    assert_eq!(
+        source(ism.lookup_token_with(0, 9, LookupOptions::default())),
+        Some("file1.js")
+    );
+    assert_eq!(
+        source(ism.lookup_token_with(
+            0,
+            9,
+            LookupOptions {
+                bias: Bias::LeastUpperBound,
+                same_line_only: true,
+            }
+        )),
+        None
+    );
+    assert_eq!(
+        source(ism.lookup_token_with(
+            1,
+            0,
+            LookupOptions {
+                bias: Bias::LeastUpperBound,
+                same_line_only: true,
+            }
+        )),
+        Some("file2.js")
+    );
+}
+
+#[test]
+fn test_indexed_sourcemap_duplicate_offsets() {
+    use sourcemap::LookupOptions;
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "map": {"version":3,"sources":["file1.js"],"names":[],"mappings":"AAAA"}
+            },
+            {
+                "offset": {"line": 1, "column": 0},
+                "map": {"version":3,"sources":["file2.js"],"names":[],"mappings":"AAAA"}
+            },
+            {
+                "offset": {"line": 1, "column": 0},
+                "map": {"version":3,"sources":["file3.js"],"names":[],"mappings":"AAAA"}
+            }
+        ]
+    }"#;
+    let ism = SourceMapIndex::from_reader(input).unwrap();
+
+    let positions = [(0, 0), (1, 0), (1, 4), (2, 0)];
+    for &(line, col) in &positions {
+        let expected = ism.lookup_token(line, col).and_then(|t| t.get_source());
+        assert!(expected.is_some());
+        let token = ism.lookup_token_with(line, col, LookupOptions::default());
+        assert_eq!(token.and_then(|t| t.get_source()), expected);
+    }
+    let tokens = ism.lookup_tokens(&positions);
+    for (&(line, col), token) in positions.iter().zip(tokens) {
+        assert_eq!(
+            token.and_then(|t| t.get_source()),
+            ism.lookup_token(line, col).and_then(|t| t.get_source())
+        );
+    }
+    assert_eq!(
+        ism.lookup_token(1, 0).and_then(|t| t.get_source()),
+        Some("file2.js")
+    );
+}
+
+#[test]
+fn test_indexed_sourcemap_like() {
+    use sourcemap::SourceMapLike;
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sections": [
+            {
+                "offset": {"line": 0, "column": 0},
+                "map": {
+                    "version":3,
+                    "sources":["file1.js"],
+                    "sourcesContent":["var a;"],
+                    "names":[],
+                    "mappings":"AAAA,EAAC"
+                }
+            },
+            {
+                "offset": {"line": 1, "column": 5},
+                "map": {
+                    "version":3,
+                    "sources":["file2.js"],
+                    "names":[],
+                    "mappings":"EAAA"
+                }
+            }
+        ]
+    }"#;
+    let maps: Vec<Box<dyn SourceMapLike>> = vec![
+        Box::new(SourceMapIndex::from_reader(input).unwrap()),
+        Box::new(DecodedMap::from_reader(input).unwrap()),
+    ];
+
+    for map in &maps {
+        assert_eq!(
+            map.sources().collect::<Vec<_>>(),
+            vec!["file1.js", "file2.js"]
+        );
+        assert_eq!(
+            map.source_contents().collect::<Vec<_>>(),
+            vec![Some("var a;"), None]
+        );
+        assert_eq!(map.tokens().count(), 3);
+        assert_eq!(
+            map.lookup_token(1, 7).and_then(|token| token.get_source()),
+            Some("file2.js")
+        );
+        assert_eq!(map.original_function_name(0, 0, Some("a"), None), None);
+    }
+}
@@ -35,3 +35,194 @@ fn test_basic_sourcemap() {
         ("coolstuff.js", 2, 8, None)
     );
 }
+
+#[test]
+fn test_shared_between_threads() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["coolstuff.js"],
+        "sourcesContent": ["var x = 1;\nalert(x);"],
+        "names": ["x","alert"],
+        "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+    }"#;
+    let sm = Arc::new(SourceMap::from_reader(input).unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let sm = sm.clone();
+            thread::spawn(move || {
+                let token = sm.lookup_token(0, 3).unwrap();
+                let line = token.get_source_view().unwrap().get_line(1).unwrap();
+                (token.get_name().map(str::to_owned), line.to_owned())
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        assert_eq!(
+            handle.join().unwrap(),
+            (Some("x".to_string()), "alert(x);".to_string())
+        );
+    }
+}
+
+#[test]
+fn test_lookup_tokens() {
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["coolstuff.js"],
+        "names": ["x","alert"],
+        "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+
+    let positions = [(1, 20), (0, 0), (0, 3), (5, 0), (0, 3), (0, 12), (1, 0)];
+    let expected: Vec<_> = positions
+        .iter()
+        .map(|&(line, col)| sm.lookup_token(line, col))
+        .collect();
+    assert_eq!(sm.lookup_tokens(&positions), expected);
+}
+
+#[test]
+fn test_lookup_token_with_options() {
+    use sourcemap::{Bias, LookupOptions};
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["coolstuff.js"],
+        "names": ["x","alert"],
+        "mappings": "AAAA,GAAIA,GAAI,EACR;;IAAIA,GAAK"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+    let glb = LookupOptions::default();
+    let lub = LookupOptions {
+        bias: Bias::LeastUpperBound,
+        ..Default::default()
+    };
+    let dst = |token: Option<sourcemap::Token<'_>>| token.map(|t| t.get_dst());
+
+    assert_eq!(dst(sm.lookup_token_with(0, 5, glb)), Some((0, 3)));
+    assert_eq!(dst(sm.lookup_token_with(0, 5, lub)), Some((0, 6)));
+    assert_eq!(dst(sm.lookup_token_with(0, 6, lub)), Some((0, 6)));
+    assert_eq!(dst(sm.lookup_token_with(0, 20, lub)), Some((2, 4)));
+    assert_eq!(dst(sm.lookup_token_with(2, 20, lub)), None);
+    assert_eq!(dst(sm.lookup_token_with(2, 1, glb)), Some((0, 8)));
+    assert_eq!(
+        sm.lookup_token_with(2, 1, glb).map(|t| t.get_dst()),
+        sm.lookup_token(2, 1).map(|t| t.get_dst())
+    );
+
+    let same_line = |bias| LookupOptions {
+        bias,
+        same_line_only: true,
+    };
+    assert_eq!(
+        dst(sm.lookup_token_with(2, 1, same_line(Bias::GreatestLowerBound))),
+        None
+    );
+    assert_eq!(
+        dst(sm.lookup_token_with(2, 1, same_line(Bias::LeastUpperBound))),
+        Some((2, 4))
+    );
+    assert_eq!(
+        dst(sm.lookup_token_with(0, 20, same_line(Bias::LeastUpperBound))),
+        None
+    );
+    assert_eq!(
+        dst(sm.lookup_token_with(2, 5, same_line(Bias::GreatestLowerBound))),
+        Some((2, 4))
+    );
+}
+
+#[test]
+fn test_owned_token() {
+    use sourcemap::OwnedToken;
+
+    fn lookup(line: u32, col: u32) -> Option<OwnedToken> {
+        let input: &[_] = br#"{
+            "version": 3,
+            "sources": ["coolstuff.js"],
+            "sourcesContent": ["var x = 1;\nalert(x);"],
+            "names": ["x","alert"],
+            "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+        }"#;
+        let sm = SourceMap::from_reader(input).unwrap();
+        let token = sm.lookup_token(line, col)?;
+        assert_eq!(OwnedToken::from(token), token.to_owned_token());
+        Some(token.to_owned_token())
+    }
+
+    let token = lookup(0, 3).unwrap();
+    assert_eq!(token.to_tuple(), ("coolstuff.js", 0, 4, Some("x")));
+    assert_eq!(token.get_dst(), (0, 3));
+    assert_eq!(token.get_context_line(), Some("var x = 1;"));
+    assert_eq!(token.to_string(), "coolstuff.js:0:4 name=x");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_owned_token_serde() {
+    use sourcemap::OwnedToken;
+
+    let input: &[_] = br#"{
+        "version": 3,
+        "sources": ["coolstuff.js"],
+        "sourcesContent": ["var x = 1;\nalert(x);"],
+        "names": ["x","alert"],
+        "mappings": "AAAA,GAAIA,GAAI,EACR,IAAIA,GAAK,EAAG,CACVC,MAAM"
+    }"#;
+    let sm = SourceMap::from_reader(input).unwrap();
+    let token = sm.lookup_token(0, 3).unwrap().to_owned_token();
+
+    let json = serde_json::to_string(&token).unwrap();
+    assert_eq!(
+        json,
+        r#"{"dst_line":0,"dst_col":3,"src_line":0,"src_col":4,"source":"coolstuff.js","name":"x","context_line":"var x = 1;"}"#
+    );
+    let roundtripped: OwnedToken = serde_json::from_str(&json).unwrap();
+    assert_eq!(roundtripped, token);
+
+    let token: OwnedToken =
+        serde_json::from_str(r#"{"dst_line":1,"dst_col":2,"src_line":3,"src_col":4}"#).unwrap();
+    assert_eq!(token.get_source(), None);
+    assert_eq!(token.get_name(), None);
+
+    let bytes = bincode::serialize(&token).unwrap();
+    assert_eq!(bincode::deserialize::<OwnedToken>(&bytes).unwrap(), token);
+}
+
+#[test]
+fn test_token_context() {
+    let source = format!(
+        "function add(a, b) {{\n  return a + b;\n}}\nvar x = '{}' + add(1, 2);\n",
+        "x".repeat(200)
+    );
+    let input = format!(
+        r#"{{"version":3,"sources":["add.js"],"sourcesContent":[{}],"names":[],"mappings":"AAAA;AACE;AACF;AACA,wNAAwN"}}"#,
+        serde_json::to_string(&source).unwrap()
+    );
+    let sm = SourceMap::from_slice(input.as_bytes()).unwrap();
+
+    let token = sm.lookup_token(1, 0).unwrap();
+    let context = token.get_context(1, 1).unwrap();
+    assert_eq!(context.get_pre_context(), &["function add(a, b) {"]);
+    assert_eq!(context.get_context_line(), "  return a + b;");
+    assert_eq!(context.get_post_context(), &["}"]);
+
+    // long lines are trimmed around the original column
+    let token = sm.lookup_token(4, 0).unwrap();
+    assert_eq!(token.get_src(), (3, 216));
+    let context = token.get_context(0, 0).unwrap();
+    let line = context.get_context_line();
+    assert!(line.starts_with("{snip} "));
+    assert!(line.ends_with(" + add(1, 2);"));
+
+    let sm =
+        SourceMap::from_slice(br#"{"version":3,"sources":["a.js"],"names":[],"mappings":"AAAA"}"#)
+            .unwrap();
+    assert_eq!(sm.lookup_token(0, 0).unwrap().get_context(1, 1), None);
+}
@@ -0,0 +1,233 @@
+use std::rc::Rc;
+
+use sourcemap::stacktrace::StackTrace;
+use sourcemap::{DecodedMap, SourceView};
+
+const INPUT: &[u8] = br#"{"version":3,"file":"test.min.js","sources":["test.js"],"names":["makeAFailure","testingStuff","Error","onSuccess","data","onFailure","invoke","cb","failed","test","value"],"mappings":"AAAA,GAAIA,cAAe,WACjB,QAASC,KACP,GAAIA,GAAe,EACnB,MAAM,IAAIC,OAAMD,GAGlB,QAASE,GAAUC,GACjBH,IAGF,QAASI,GAAUD,GACjB,KAAM,IAAIF,OAAM,WAGlB,QAASI,GAAOF,GACd,GAAIG,GAAK,IACT,IAAIH,EAAKI,OAAQ,CACfD,EAAKF,MACA,CACLE,EAAKJ,EAEPI,EAAGH,GAGL,QAASK,KACP,GAAIL,IAAQI,OAAQ,KAAME,MAAO,GACjCJ,GAAOF,GAGT,MAAOK","sourcesContent":["var makeAFailure = (function() {\n  function testingStuff() {\n    var testingStuff = 42;\n    throw new Error(testingStuff);\n  }\n\n  function onSuccess(data) {\n    testingStuff();\n  }\n\n  function onFailure(data) {\n    throw new Error('failed!');\n  }\n\n  function invoke(data) {\n    var cb = null;\n    if (data.failed) {\n      cb = onFailure;\n    } else {\n      cb = onSuccess;\n    }\n    cb(data);\n  }\n\n  function test() {\n    var data = {failed: true, value: 42};\n    invoke(data);\n  }\n\n  return test;\n})();\n"]}"#;
+const MINIFIED: &str = r#"var makeAFailure=function(){function n(){var n=42;throw new Error(n)}function r(r){n()}function e(n){throw new Error("failed!")}function i(n){var i=null;if(n.failed){i=e}else{i=r}i(n)}function u(){var n={failed:true,value:42};i(n)}return u}();"#;
+
+#[test]
+fn test_parse_v8() {
+    let trace = StackTrace::parse(
+        "TypeError: Cannot read properties of undefined (reading 'x')
+    at foo (http://example.com/app.js:10:5)
+    at Object.<anonymous> (/srv/app/index.js:3:1)
+    at new Thing (http://example.com/app.js:1:2)
+    at async Promise.all (index 0)
+    at async run (file:///srv/app/main.mjs:7:11)
+    at http://example.com/app.js:20:3
+    at Array.forEach (native)
+    at eval (eval at compile (http://example.com/app.js:30:9), <anonymous>:1:5)
+    at <anonymous>",
+    );
+    assert_eq!(
+        trace.get_message(),
+        Some("TypeError: Cannot read properties of undefined (reading 'x')")
+    );
+    let frames: Vec<_> = trace
+        .frames()
+        .iter()
+        .map(|f| {
+            (
+                f.get_function(),
+                f.get_url(),
+                f.get_line(),
+                f.get_column(),
+                f.is_async(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        frames,
+        vec![
+            (
+                Some("foo"),
+                Some("http://example.com/app.js"),
+                Some(10),
+                Some(5),
+                false
+            ),
+            (
+                Some("Object.<anonymous>"),
+                Some("/srv/app/index.js"),
+                Some(3),
+                Some(1),
+                false
+            ),
+            (
+                Some("new Thing"),
+                Some("http://example.com/app.js"),
+                Some(1),
+                Some(2),
+                false
+            ),
+            (
+                Some("run"),
+                Some("file:///srv/app/main.mjs"),
+                Some(7),
+                Some(11),
+                true
+            ),
+            (
+                None,
+                Some("http://example.com/app.js"),
+                Some(20),
+                Some(3),
+                false
+            ),
+            (Some("Array.forEach"), None, None, None, false),
+            (
+                Some("eval"),
+                Some("http://example.com/app.js"),
+                Some(30),
+                Some(9),
+                false
+            ),
+            (None, Some("<anonymous>"), None, None, false),
+        ]
+    );
+    assert!(trace.frames()[5].is_native());
+    assert!(trace.frames()[6].is_eval());
+}
+
+#[test]
+fn test_parse_spidermonkey() {
+    let trace = StackTrace::parse(
+        "foo@http://example.com/app.js:10:5
+bar/<@http://example.com/app.js:11:6
+async*run@http://example.com/app.js:12:7
+@http://example.com/app.js:13:8
+evaled@http://example.com/app.js line 30 > eval:1:5
+",
+    );
+    assert_eq!(trace.get_message(), None);
+    let frames = trace.frames();
+    assert_eq!(frames.len(), 5);
+    assert_eq!(frames[0].get_function(), Some("foo"));
+    assert_eq!(frames[1].get_function(), Some("bar/<"));
+    assert!(frames[2].is_async());
+    assert_eq!(frames[2].get_function(), Some("run"));
+    assert_eq!(frames[3].get_function(), None);
+    assert_eq!(frames[3].get_line(), Some(13));
+    assert!(frames[4].is_eval());
+    assert_eq!(frames[4].get_url(), Some("http://example.com/app.js"));
+    assert_eq!(
+        (frames[4].get_line(), frames[4].get_column()),
+        (Some(30), None)
+    );
+}
+
+#[test]
+fn test_parse_javascriptcore() {
+    let trace = StackTrace::parse(
+        "foo@http://example.com/app.js:10:5
+eval code@
+eval@[native code]
+global code@http://example.com/app.js:20:1
+http://example.com/app.js:21:2
+[native code]",
+    );
+    let frames = trace.frames();
+    assert_eq!(frames.len(), 6);
+    assert_eq!(frames[0].get_function(), Some("foo"));
+    assert!(frames[1].is_eval());
+    assert!(frames[2].is_native());
+    assert_eq!(frames[3].get_function(), None);
+    assert_eq!(frames[3].get_line(), Some(20));
+    assert_eq!(frames[4].get_url(), Some("http://example.com/app.js"));
+    assert_eq!(frames[4].get_column(), Some(2));
+    assert!(frames[5].is_native());
+}
+
+#[test]
+fn test_message_with_at_sign() {
+    let trace = StackTrace::parse("Error: no user@example.com\n    at foo (app.js:1:1)");
+    assert_eq!(trace.get_message(), Some("Error: no user@example.com"));
+    assert_eq!(trace.frames().len(), 1);
+}
+
+#[test]
+fn test_message_with_host_and_port() {
+    let trace = StackTrace::parse(
+        "Error: connect ECONNREFUSED 127.0.0.1:3000
+    at TCPConnectWrap.afterConnect [as oncomplete] (node:net:1555:16)",
+    );
+    assert_eq!(
+        trace.get_message(),
+        Some("Error: connect ECONNREFUSED 127.0.0.1:3000")
+    );
+    assert_eq!(trace.frames().len(), 1);
+
+    let trace = StackTrace::parse("Error: connect ECONNREFUSED 127.0.0.1:3000\nlocalhost:3000:1");
+    assert_eq!(trace.frames().len(), 0);
+}
+
+#[test]
+fn test_symbolicate() {
+    let sv = SourceView::new(MINIFIED);
+    let sm = DecodedMap::from_reader(INPUT).unwrap();
+
+    let trace = StackTrace::parse(
+        "Error: failed!
+    at e (http://example.com/test.min.js:1:108)
+    at i (http://example.com/test.min.js:1:180)
+    at Object.u [as run] (http://example.com/test.min.js:1:227)
+    at other (http://example.com/other.js:1:1)",
+    );
+    let symbolicated = trace.symbolicate(|url| {
+        if url == "http://example.com/test.min.js" {
+            Some((&sm, Some(&sv)))
+        } else {
+            None
+        }
+    });
+
+    let frames = symbolicated.frames();
+    assert!(frames[0].is_mapped());
+    assert_eq!(frames[0].get_function(), Some("onFailure"));
+    assert_eq!(frames[0].get_source(), Some("test.js"));
+    assert_eq!(frames[1].get_function(), Some("invoke"));
+    assert_eq!(frames[2].get_function(), Some("test"));
+    assert!(!frames[3].is_mapped());
+    assert_eq!(frames[3].get_function(), Some("other"));
+
+    let rendered = symbolicated.to_string();
+    let lines: Vec<_> = rendered.lines().collect();
+    assert_eq!(lines[0], "Error: failed!");
+    assert_eq!(
+        lines[1],
+        format!(
+            "    at onFailure (test.js:{}:{})",
+            frames[0].get_line().unwrap(),
+            frames[0].get_column().unwrap()
+        )
+    );
+    assert_eq!(lines[4], "    at other (http://example.com/other.js:1:1)");
+}
+
+#[test]
+fn test_symbolicate_owned() {
+    let trace = StackTrace::parse(
+        "Error: failed!
+    at e (http://example.com/test.min.js:1:108)
+    at new i (http://example.com/test.min.js:1:180)",
+    );
+    let symbolicated = trace.symbolicate(|_| {
+        let sm = DecodedMap::from_reader(INPUT).ok()?;
+        Some((sm, Some(SourceView::from_string(MINIFIED.to_string()))))
+    });
+
+    let frames = symbolicated.frames();
+    assert_eq!(frames[0].get_function(), Some("onFailure"));
+    assert_eq!(frames[1].get_function(), Some("invoke"));
+
+    let cached = Rc::new(DecodedMap::from_reader(INPUT).unwrap());
+    let symbolicated = trace.symbolicate(|_| Some((cached.clone(), None::<&SourceView>)));
+    assert!(symbolicated.frames()[1].is_mapped());
+}
+
+#[test]
+fn test_render_raw() {
+    let input = "Error: boom\n    at async foo (app.js:1:2)\n    at app.js:3:4\n    at Array.map (native)\n";
+    assert_eq!(StackTrace::parse(input).to_string(), input);
+}